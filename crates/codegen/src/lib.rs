@@ -63,7 +63,7 @@ pub fn codegen(
         quote! {
             #![allow(clippy::redundant_clone, clippy::wrong_self_convention)]
             #![allow(dead_code)]
-            use microtree::{Red, Ast, AstBuilder, Cache, TokenBuilder, Green, AliasBuilder, IntoBuilder};
+            use microtree::{kind, Red, Ast, AstBuilder, GreenBuilder, TokenBuilder, Green, AliasBuilder, IntoBuilder};
         }
     )?;
 
@@ -97,7 +97,7 @@ pub fn codegen(
                 quote! (
                     impl IntoBuilder<#alias_ty> for TokenBuilder<#token_name> {
                         fn into_builder(self) -> AliasBuilder<Self, #alias_ty> {
-                            AliasBuilder::new(#alias, self)
+                            AliasBuilder::new(kind!(#alias), self)
                         }
                     }
                 )
@@ -112,7 +112,7 @@ pub fn codegen(
                 pub struct #token_name(Red);
                 impl Ast for #token_name {
                     fn new(node: Red) -> Option<Self> {
-                        if !node.is("token") {
+                        if !node.is(kind!("token")) {
                             return None;
                         }
                         let green = node.green();
@@ -396,7 +396,7 @@ pub fn codegen(
                 quote! (
                     impl #build_generics IntoBuilder<#alias_ty> for #node_builder_name #build_generics #where_generics {
                         fn into_builder(self) -> AliasBuilder<Self, #alias_ty> {
-                            AliasBuilder::new(#alias, self)
+                            AliasBuilder::new(kind!(#alias), self)
                         }
                     }
                 )
@@ -411,7 +411,7 @@ pub fn codegen(
                 pub struct #node_name(Red);
                 impl Ast for #node_name {
                     fn new(node: Red) -> Option<Self> {
-                        if !node.is(#node_name_lit) {
+                        if !node.is(kind!(#node_name_lit)) {
                             return None;
                         }
                         node.green().as_node()?;
@@ -465,19 +465,19 @@ pub fn codegen(
                         #where_generics
                 {
                     type T = #node_name;
-                    fn build(self, builder: &mut Cache) -> #node_name {
+                    fn build(self, builder: &mut dyn GreenBuilder) -> #node_name {
                         let green = AstBuilder::build_green(self, builder);
                         #node_name::new(Red::root(green)).unwrap()
                     }
-                    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+                    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
                         AstBuilder::build_green(*self, builder)
                     }
-                    fn build_green(self, builder: &mut Cache) -> Green {
+                    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
                               let children = None.into_iter()
                                 #(#children)*
                                 .collect();
                         //let children = vec![];
-                        builder.node(#node_name_lit, move |_| children)
+                        builder.node(kind!(#node_name_lit), move |_| children)
                     }
                 }
 
@@ -557,7 +557,13 @@ impl AstField {
     }
 
     fn is_many(&self) -> bool {
-        matches!(self, Self::Node { cardinality: Cardinality::Many(_), .. })
+        matches!(
+            self,
+            Self::Node {
+                cardinality: Cardinality::Many(_),
+                ..
+            }
+        )
     }
 }
 
@@ -0,0 +1,81 @@
+//! Compares green trees allocated one by one with ones allocated in the
+//! arena of `Cache::with_arena`, on nested arrays built with the JSON example AST.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use microtree::{AstBuilder, Cache, CachePolicy, Green, IntoBuilder, TokenBuilder};
+
+// Generated code, linted with the example.
+#[allow(clippy::all)]
+#[path = "../examples/json/generated/mod.rs"]
+mod generated;
+
+use generated::*;
+
+const DEPTH: usize = 4;
+const WIDTH: usize = 10;
+
+// `is_multiple_of` needs Rust 1.87.
+#[allow(clippy::manual_is_multiple_of)]
+fn value(depth: usize, index: usize) -> Box<dyn AstBuilder<T = Value>> {
+    if depth > 0 {
+        let values = (0..WIDTH)
+            .map(|i| value(depth - 1, index * WIDTH + i))
+            .collect();
+        return Array::build()
+            .fill(
+                LBracket::build(),
+                values,
+                Comma::build(),
+                RBracket::build().with_leading("\n"),
+            )
+            .into_dyn();
+    }
+    if index % 2 == 0 {
+        let number: TokenBuilder<Number> = Number::build(index as u32 % 1000);
+        number.with_leading("\n    ").into_dyn()
+    } else {
+        String::build()
+            .fill(
+                DQuote::build().with_leading("\n    "),
+                StringVal::build(format!("value {}", index % 100)),
+                DQuote::build(),
+            )
+            .into_dyn()
+    }
+}
+
+fn document(builder: &mut Cache) -> Green {
+    value(DEPTH, 0).build_boxed_green(builder)
+}
+
+fn tokens(green: &Green) -> usize {
+    match green.as_token() {
+        Some(_) => 1,
+        None => green.children().map(|child| tokens(&child)).sum(),
+    }
+}
+
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+    group.bench_function("heap", |b| {
+        b.iter(|| black_box(document(&mut Cache::default())))
+    });
+    group.bench_function("arena", |b| {
+        b.iter(|| black_box(document(&mut Cache::with_arena(CachePolicy::default()))))
+    });
+    group.finish();
+}
+
+fn traverse(c: &mut Criterion) {
+    let heap = document(&mut Cache::default());
+    let arena = document(&mut Cache::with_arena(CachePolicy::default()));
+    assert_eq!(heap, arena);
+
+    let mut group = c.benchmark_group("traverse");
+    group.bench_function("heap", |b| b.iter(|| tokens(black_box(&heap))));
+    group.bench_function("arena", |b| b.iter(|| tokens(black_box(&arena))));
+    group.finish();
+}
+
+criterion_group!(benches, build, traverse);
+criterion_main!(benches);
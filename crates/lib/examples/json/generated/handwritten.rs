@@ -7,7 +7,7 @@ use smol_str::SmolStr;
 pub struct Number(Red);
 impl Ast for Number {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("number") {
+        if !node.is(kind!("number")) {
             return None;
         }
         node.green().as_token()?;
@@ -21,7 +21,7 @@ impl Ast for Number {
 
 impl Number {
     pub fn build(value: u32) -> TokenBuilder<Number> {
-        TokenBuilder::custom("number", value.to_string())
+        TokenBuilder::custom(kind!("number"), value.to_string())
     }
     pub fn value(&self) -> Option<u32> {
         Some(self.0.green().as_token()?.value.parse().ok()?)
@@ -30,7 +30,7 @@ impl Number {
 
 impl IntoBuilder<Value> for TokenBuilder<Number> {
     fn into_builder(self) -> AliasBuilder<Self, Value> {
-        AliasBuilder::new("Value", self)
+        AliasBuilder::new(kind!("Value"), self)
     }
 }
 
@@ -38,7 +38,7 @@ impl IntoBuilder<Value> for TokenBuilder<Number> {
 pub struct StringVal(Red);
 impl Ast for StringVal {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("string") {
+        if !node.is(kind!("string")) {
             return None;
         }
         node.green().as_token()?;
@@ -66,15 +66,18 @@ impl StringValBuilder {
 
 impl AstBuilder for StringValBuilder {
     type T = StringVal;
-    fn build(self, builder: &mut microtree::Cache) -> StringVal {
+    fn build(self, builder: &mut dyn microtree::GreenBuilder) -> StringVal {
         StringVal::new(Red::root(self.build_green(builder))).unwrap()
     }
 
-    fn build_green(self, builder: &mut microtree::Cache) -> microtree::Green {
-        builder.token("string", self.val.to_string())
+    fn build_green(self, builder: &mut dyn microtree::GreenBuilder) -> microtree::Green {
+        builder.token(kind!("string"), self.val.to_string())
     }
 
-    fn build_boxed_green(self: Box<Self>, builder: &mut microtree::Cache) -> microtree::Green {
+    fn build_boxed_green(
+        self: Box<Self>,
+        builder: &mut dyn microtree::GreenBuilder,
+    ) -> microtree::Green {
         self.build_green(builder)
     }
 }
@@ -1,6 +1,8 @@
 #![allow(clippy::redundant_clone, clippy::wrong_self_convention)]
 #![allow(dead_code)]
-use microtree::{AliasBuilder, Ast, AstBuilder, Cache, Green, IntoBuilder, Red, TokenBuilder};
+use microtree::{
+    kind, AliasBuilder, Ast, AstBuilder, Green, GreenBuilder, IntoBuilder, Red, TokenBuilder,
+};
 
 mod handwritten;
 pub use handwritten::*;
@@ -9,7 +11,7 @@ pub use handwritten::*;
 pub struct DQuote(Red);
 impl Ast for DQuote {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -33,7 +35,7 @@ impl DQuote {
 pub struct Comma(Red);
 impl Ast for Comma {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -57,7 +59,7 @@ impl Comma {
 pub struct LBracket(Red);
 impl Ast for LBracket {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -81,7 +83,7 @@ impl LBracket {
 pub struct RBracket(Red);
 impl Ast for RBracket {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -161,7 +163,7 @@ impl Value {
 pub struct Array(Red);
 impl Ast for Array {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("Array") {
+        if !node.is(kind!("Array")) {
             return None;
         }
         node.green().as_node()?;
@@ -244,14 +246,14 @@ where
     T2: AstBuilder<T = RBracket>,
 {
     type T = Array;
-    fn build(self, builder: &mut Cache) -> Array {
+    fn build(self, builder: &mut dyn GreenBuilder) -> Array {
         let green = AstBuilder::build_green(self, builder);
         Array::new(Red::root(green)).unwrap()
     }
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
         AstBuilder::build_green(*self, builder)
     }
-    fn build_green(self, builder: &mut Cache) -> Green {
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
         let children = None
             .into_iter()
             .chain(self.lbracket.map(|it| it.build_green(builder)).into_iter())
@@ -269,7 +271,7 @@ where
             })
             .chain(self.rbracket.map(|it| it.build_green(builder)).into_iter())
             .collect();
-        builder.node("Array", move |_| children)
+        builder.node(kind!("Array"), move |_| children)
     }
 }
 impl<T0, T1, T2> IntoBuilder<Value> for ArrayBuilder<T0, T1, T2>
@@ -279,7 +281,7 @@ where
     T2: AstBuilder<T = RBracket>,
 {
     fn into_builder(self) -> AliasBuilder<Self, Value> {
-        AliasBuilder::new("Value", self)
+        AliasBuilder::new(kind!("Value"), self)
     }
 }
 
@@ -287,7 +289,7 @@ where
 pub struct String(Red);
 impl Ast for String {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("String") {
+        if !node.is(kind!("String")) {
             return None;
         }
         node.green().as_node()?;
@@ -361,14 +363,14 @@ where
     T2: AstBuilder<T = DQuote>,
 {
     type T = String;
-    fn build(self, builder: &mut Cache) -> String {
+    fn build(self, builder: &mut dyn GreenBuilder) -> String {
         let green = AstBuilder::build_green(self, builder);
         String::new(Red::root(green)).unwrap()
     }
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
         AstBuilder::build_green(*self, builder)
     }
-    fn build_green(self, builder: &mut Cache) -> Green {
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
         let children = None
             .into_iter()
             .chain(
@@ -383,7 +385,7 @@ where
                     .into_iter(),
             )
             .collect();
-        builder.node("String", move |_| children)
+        builder.node(kind!("String"), move |_| children)
     }
 }
 impl<T0, T1, T2> IntoBuilder<Value> for StringBuilder<T0, T1, T2>
@@ -393,6 +395,6 @@ where
     T2: AstBuilder<T = DQuote>,
 {
     fn into_builder(self) -> AliasBuilder<Self, Value> {
-        AliasBuilder::new("Value", self)
+        AliasBuilder::new(kind!("Value"), self)
     }
 }
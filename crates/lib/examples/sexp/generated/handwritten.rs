@@ -7,7 +7,7 @@ use smol_str::SmolStr;
 pub struct Atom(Red);
 impl Ast for Atom {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("atom") {
+        if !node.is(kind!("atom")) {
             return None;
         }
         node.green().as_token()?;
@@ -21,12 +21,12 @@ impl Ast for Atom {
 
 impl Atom {
     pub fn build(value: impl Into<SmolStr>) -> TokenBuilder<Atom> {
-        TokenBuilder::custom("atom", value)
+        TokenBuilder::custom(kind!("atom"), value)
     }
 }
 
 impl IntoBuilder<Value> for TokenBuilder<Atom> {
     fn into_builder(self) -> AliasBuilder<Self, Value> {
-        AliasBuilder::new("Value", self)
+        AliasBuilder::new(kind!("Value"), self)
     }
 }
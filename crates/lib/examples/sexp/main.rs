@@ -37,7 +37,7 @@ fn main() {
         .into_builder()
         .build(&mut builder);
 
-    println!("DEBUG: {:?}\n", sexp);
+    println!("DEBUG: {:#?}\n", sexp);
 
     println!("ESCAPED:");
     print(&sexp);
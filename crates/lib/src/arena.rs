@@ -0,0 +1,314 @@
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::ptr::{self, addr_of, addr_of_mut, NonNull};
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::GreenData;
+
+const CHUNK_SLOTS: usize = 512;
+
+/// Reference counted pointer behind every [`Green`](crate::Green).
+///
+/// Elements live in their own `Arc`, or in a chunk of an [`Arena`] when built
+/// by a cache with one.
+#[derive(Clone)]
+pub(crate) enum GreenPtr {
+    Heap(Arc<GreenData>),
+    Arena(SlotPtr),
+}
+
+impl GreenPtr {
+    pub(crate) fn new(data: GreenData) -> Self {
+        GreenPtr::Heap(Arc::new(data))
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const GreenData {
+        &**self
+    }
+
+    pub(crate) fn ptr_eq(&self, other: &GreenPtr) -> bool {
+        match (self, other) {
+            (GreenPtr::Heap(left), GreenPtr::Heap(right)) => Arc::ptr_eq(left, right),
+            (GreenPtr::Arena(left), GreenPtr::Arena(right)) => left.0 == right.0,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn strong_count(&self) -> usize {
+        match self {
+            GreenPtr::Heap(arc) => Arc::strong_count(arc),
+            GreenPtr::Arena(slot) => slot.slot().count.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Moves the element out if this is its only reference.
+    #[cfg(feature = "serde")]
+    pub(crate) fn try_unwrap(self) -> Result<GreenData, Self> {
+        match self {
+            GreenPtr::Heap(arc) => Arc::try_unwrap(arc).map_err(GreenPtr::Heap),
+            GreenPtr::Arena(slot) => slot.try_unwrap().map_err(GreenPtr::Arena),
+        }
+    }
+}
+
+impl Deref for GreenPtr {
+    type Target = GreenData;
+
+    fn deref(&self) -> &GreenData {
+        match self {
+            GreenPtr::Heap(arc) => arc,
+            GreenPtr::Arena(slot) => &slot.slot().data,
+        }
+    }
+}
+
+/// Reference counted pointer to an element in a chunk of an [`Arena`].
+pub(crate) struct SlotPtr(NonNull<Slot>);
+
+struct Slot {
+    count: AtomicUsize,
+    chunk: NonNull<Chunk>,
+    data: GreenData,
+}
+
+struct Chunk {
+    /// Live elements, plus one while the arena still allocates from it.
+    live: AtomicUsize,
+    slots: [MaybeUninit<Slot>; CHUNK_SLOTS],
+}
+
+// Elements are immutable once written and both counters are atomic.
+unsafe impl Send for SlotPtr {}
+unsafe impl Sync for SlotPtr {}
+
+impl SlotPtr {
+    fn slot(&self) -> &Slot {
+        // Safety: the slot stays alive while `self` holds a reference to it.
+        unsafe { self.0.as_ref() }
+    }
+
+    #[cfg(feature = "serde")]
+    fn try_unwrap(self) -> Result<GreenData, Self> {
+        if self
+            .slot()
+            .count
+            .compare_exchange(1, 0, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(self);
+        }
+        let this = std::mem::ManuallyDrop::new(self);
+        let slot = this.0.as_ptr();
+        // Safety: the count dropped to zero, nothing else can read the slot.
+        unsafe {
+            let data = ptr::read(addr_of!((*slot).data));
+            Chunk::release((*slot).chunk);
+            Ok(data)
+        }
+    }
+}
+
+impl Clone for SlotPtr {
+    fn clone(&self) -> Self {
+        if self.slot().count.fetch_add(1, Ordering::Relaxed) > isize::MAX as usize {
+            std::process::abort();
+        }
+        Self(self.0)
+    }
+}
+
+impl Drop for SlotPtr {
+    fn drop(&mut self) {
+        if self.slot().count.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        atomic::fence(Ordering::Acquire);
+        let slot = self.0.as_ptr();
+        // Safety: this was the last reference. Children are dropped before the
+        // slot is given back, so a chunk outlives elements referring into it.
+        unsafe {
+            ptr::drop_in_place(addr_of_mut!((*slot).data));
+            Chunk::release((*slot).chunk);
+        }
+    }
+}
+
+impl Chunk {
+    fn alloc() -> NonNull<Chunk> {
+        let mut chunk = Box::<Chunk>::new_uninit();
+        // Safety: `slots` is an array of `MaybeUninit`, it needs no initialization.
+        let chunk = unsafe {
+            addr_of_mut!((*chunk.as_mut_ptr()).live).write(AtomicUsize::new(1));
+            chunk.assume_init()
+        };
+        NonNull::from(Box::leak(chunk))
+    }
+
+    /// Drops one reference to the chunk, freeing it after the last one.
+    unsafe fn release(chunk: NonNull<Chunk>) {
+        // Only the counter is borrowed, other threads may be reading slots.
+        let live = &*addr_of!((*chunk.as_ptr()).live);
+        if live.fetch_sub(1, Ordering::Release) == 1 {
+            atomic::fence(Ordering::Acquire);
+            drop(Box::from_raw(chunk.as_ptr()));
+        }
+    }
+}
+
+/// Bump allocator for green elements, owned by a [`Cache`](crate::Cache).
+///
+/// Elements are placed next to each other in fixed size chunks instead of one
+/// allocation each. Every chunk counts its live elements and is freed once all
+/// of them are dropped and the arena has moved on, so trees can outlive the
+/// cache that built them.
+pub(crate) struct Arena {
+    chunk: NonNull<Chunk>,
+    used: usize,
+}
+
+// The chunk is only written through `&mut self`.
+unsafe impl Send for Arena {}
+unsafe impl Sync for Arena {}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self {
+            chunk: Chunk::alloc(),
+            used: 0,
+        }
+    }
+}
+
+impl Arena {
+    pub(crate) fn alloc(&mut self, data: GreenData) -> GreenPtr {
+        if self.used == CHUNK_SLOTS {
+            let full = std::mem::replace(&mut self.chunk, Chunk::alloc());
+            self.used = 0;
+            // Safety: gives up the reference the arena held on the full chunk.
+            unsafe { Chunk::release(full) };
+        }
+        let chunk = self.chunk.as_ptr();
+        // Safety: slot `used` was never handed out, elements in other slots are
+        // only read through their own pointers.
+        unsafe {
+            (*addr_of!((*chunk).live)).fetch_add(1, Ordering::Relaxed);
+            let slot = (addr_of_mut!((*chunk).slots) as *mut Slot).add(self.used);
+            slot.write(Slot {
+                count: AtomicUsize::new(1),
+                chunk: self.chunk,
+                data,
+            });
+            self.used += 1;
+            GreenPtr::Arena(SlotPtr(NonNull::new_unchecked(slot)))
+        }
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        // Safety: the arena's own reference, taken in `Chunk::alloc`.
+        unsafe { Chunk::release(self.chunk) }
+    }
+}
+
+impl fmt::Debug for Arena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena").field("used", &self.used).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn document(builder: &mut Cache, len: usize) -> Green {
+        builder.node("Array", |builder| {
+            (0..len)
+                .map(|i| {
+                    builder.alias("Value", |builder| {
+                        builder.node("String", |builder| {
+                            vec![
+                                builder.token("token", "\""),
+                                builder.token("string", i.to_string()),
+                                builder.token("token", "\""),
+                            ]
+                        })
+                    })
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn arena_trees_use_green_api() {
+        let mut builder = Cache::with_arena(CachePolicy::default());
+        let green = document(&mut builder, 2000);
+        let mut heap = Cache::default();
+
+        assert_eq!(green, document(&mut heap, 2000));
+        let again = document(&mut builder, 2000);
+        assert!(again
+            .children()
+            .nth(7)
+            .unwrap()
+            .ptr_eq(&green.children().nth(7).unwrap()));
+
+        let root = Red::root(green.clone());
+        let value = root.nth_child(1500).unwrap();
+        assert!(value.is("Value") && value.is("String"));
+        let string = value.nth_child(1).unwrap();
+        let one = builder.token("string", "one");
+        let new = string.replace(&mut builder, one);
+        assert!(new.to_string().contains("\"one\""));
+        assert!(new
+            .children()
+            .nth(3)
+            .unwrap()
+            .ptr_eq(&green.children().nth(3).unwrap()));
+    }
+
+    #[test]
+    fn arena_trees_outlive_cache() {
+        let mut builder = Cache::with_arena(CachePolicy::default());
+        let green = document(&mut builder, 1000);
+        let first = green.children().next().unwrap();
+        drop(builder);
+        drop(green);
+
+        assert_eq!("\"0\"", first.to_string());
+        let roots = std::thread::scope(|scope| {
+            (0..4)
+                .map(|_| scope.spawn(|| first.clone()))
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert!(roots.iter().all(|root| root.ptr_eq(&first)));
+    }
+
+    #[test]
+    fn heap_and_arena_elements() {
+        let mut heap = Cache::default();
+        let one = heap.token("number", "1");
+        let mut builder = Cache::with_arena(CachePolicy::default());
+        let list = builder.node("List", |_| vec![one.clone(), one.clone()]);
+        drop(builder);
+
+        assert!(list.children().next().unwrap().ptr_eq(&one));
+        let same = heap.node("List", |_| vec![one.clone(), one.clone()]);
+        assert_eq!(same, list);
+        assert!(!same.ptr_eq(&list));
+    }
+
+    #[test]
+    fn arena_collect() {
+        let mut builder = Cache::with_arena(CachePolicy::default());
+        let green = document(&mut builder, 600);
+        let entries = builder.size();
+
+        drop(green);
+        assert_eq!(entries, builder.collect());
+        assert_eq!(0, builder.size());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Cache, Green, Name, Red};
+use crate::{Green, GreenBuilder, Kind, Red, Trivia};
 use smol_str::SmolStr;
 use std::marker::PhantomData;
 
@@ -9,22 +9,22 @@ pub trait Ast: Sized {
 
 pub trait AstBuilder {
     type T;
-    fn build(self, builder: &mut Cache) -> Self::T;
-    fn build_green(self, builder: &mut Cache) -> Green;
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green;
+    fn build(self, builder: &mut dyn GreenBuilder) -> Self::T;
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green;
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green;
 }
 
 pub struct TokenBuilder<A> {
-    leading: Option<SmolStr>,
-    name: Name,
+    leading: Option<Trivia>,
+    name: Kind,
     token: SmolStr,
-    trailing: Option<SmolStr>,
+    trailing: Option<Trivia>,
     _phantom: PhantomData<A>,
 }
 impl<A> TokenBuilder<A> {
-    pub fn custom(name: Name, token: impl Into<SmolStr>) -> Self {
+    pub fn custom(name: impl Into<Kind>, token: impl Into<SmolStr>) -> Self {
         Self {
-            name,
+            name: name.into(),
             token: token.into(),
             leading: Default::default(),
             trailing: Default::default(),
@@ -32,18 +32,18 @@ impl<A> TokenBuilder<A> {
         }
     }
     pub fn new(token: impl Into<SmolStr>) -> Self {
-        Self::custom("token", token)
+        Self::custom(crate::kind!("token"), token)
     }
-    pub fn with_leading(mut self, leading: impl Into<SmolStr>) -> Self {
+    pub fn with_leading(mut self, leading: impl Into<Trivia>) -> Self {
         self.leading = Some(leading.into());
         self
     }
-    pub fn with_trailing(mut self, trailing: impl Into<SmolStr>) -> Self {
+    pub fn with_trailing(mut self, trailing: impl Into<Trivia>) -> Self {
         self.trailing = Some(trailing.into());
         self
     }
 
-    pub fn build_token(self, builder: &mut Cache) -> Green {
+    pub fn build_token(self, builder: &mut dyn GreenBuilder) -> Green {
         let leading = self.leading.unwrap_or_default();
         let trailing = self.trailing.unwrap_or_default();
         builder.with_trivia(self.name, leading, self.token, trailing)
@@ -55,15 +55,15 @@ where
     T: Ast,
 {
     type T = T;
-    fn build_green(self, builder: &mut Cache) -> Green {
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
         self.build_token(builder)
     }
 
-    fn build(self, builder: &mut Cache) -> T {
+    fn build(self, builder: &mut dyn GreenBuilder) -> T {
         T::new(Red::root(self.build_token(builder))).unwrap()
     }
 
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
         self.build_token(builder)
     }
 }
@@ -73,7 +73,7 @@ where
     B: AstBuilder,
     As: Ast,
 {
-    alias: Name,
+    alias: Kind,
     builder: B,
     _phantom: PhantomData<As>,
 }
@@ -83,9 +83,9 @@ where
     B: AstBuilder,
     As: Ast,
 {
-    pub fn new(alias: Name, builder: B) -> Self {
+    pub fn new(alias: impl Into<Kind>, builder: B) -> Self {
         Self {
-            alias,
+            alias: alias.into(),
             builder,
             _phantom: Default::default(),
         }
@@ -98,14 +98,14 @@ where
     As: Ast,
 {
     type T = As;
-    fn build(self, builder: &mut Cache) -> As {
+    fn build(self, builder: &mut dyn GreenBuilder) -> As {
         let green = AstBuilder::build_green(self, builder);
         As::new(Red::root(green)).unwrap()
     }
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
         AstBuilder::build_green(*self, builder)
     }
-    fn build_green(self, builder: &mut Cache) -> Green {
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
         let green = AstBuilder::build_green(self.builder, builder);
         builder.alias(self.alias, move |_| green)
     }
@@ -1,87 +1,364 @@
+use crate::arena::Arena;
 use crate::GreenData;
 use crate::Node;
 use crate::Token;
-use crate::{Green, GreenKind, Name};
-use std::sync::Arc;
+use crate::{Green, GreenKind, Kind, MutationError, Trivia};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
 
 use smol_str::SmolStr;
 
 #[derive(Debug, Default)]
 pub struct Cache {
-    cache: Vec<Green>,
+    cache: Interner,
+    policy: CachePolicy,
+    stats: CacheStats,
+    arena: Option<Arena>,
 }
 
-impl Cache {
-    pub fn node(&mut self, name: Name, f: impl FnOnce(&mut Self) -> Vec<Green>) -> Green {
-        let children = f(self);
-        let size = children.iter().map(|g| g.size()).sum();
-        self.add_node(GreenData {
-            name,
-            kind: GreenKind::Node(Node { children }),
-            size,
-        })
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// Nodes with more children than this are never interned.
+    pub max_node_width: Option<usize>,
+    pub tokens: bool,
+    pub aliases: bool,
+    pub nodes: bool,
+    /// Once reached, entries referenced only by the cache are evicted.
+    /// If there is nothing to evict, new entries are not interned and eviction
+    /// is retried after as many misses as there are entries.
+    pub max_entries: Option<usize>,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            max_node_width: Some(4),
+            tokens: true,
+            aliases: true,
+            nodes: true,
+            max_entries: None,
+        }
     }
+}
 
-    pub fn replace_children(&mut self, green: Green, children: Vec<Green>) -> Green {
-        match green.kind() {
-            GreenKind::Node(_) => {
-                let size = children.iter().map(|g| g.size()).sum();
-                self.add_node(GreenData {
-                    name: green.name(),
-                    size,
-                    kind: GreenKind::Node(Node { children }),
-                })
+impl CachePolicy {
+    pub fn disabled() -> Self {
+        Self {
+            max_node_width: None,
+            tokens: false,
+            aliases: false,
+            nodes: false,
+            max_entries: None,
+        }
+    }
+
+    pub fn tokens_only() -> Self {
+        Self {
+            tokens: true,
+            ..Self::disabled()
+        }
+    }
+
+    pub fn with_max_node_width(mut self, width: impl Into<Option<usize>>) -> Self {
+        self.max_node_width = width.into();
+        self
+    }
+
+    pub fn with_max_entries(mut self, entries: impl Into<Option<usize>>) -> Self {
+        self.max_entries = entries.into();
+        self
+    }
+
+    pub(crate) fn allows(&self, kind: &GreenKind) -> bool {
+        match kind {
+            GreenKind::Node(Node { children }) => {
+                self.nodes
+                    && self
+                        .max_node_width
+                        .is_none_or(|width| children.len() <= width)
             }
-            GreenKind::Alias(Some(child)) => {
-                let alias_name = green.name();
-                let child = self.replace_children(child.clone(), children);
-                self.add_node(GreenData {
-                    name: alias_name,
-                    size: child.size(),
-                    kind: GreenKind::Alias(Some(child)),
-                })
+            GreenKind::Alias(_) => self.aliases,
+            GreenKind::Token(_) => self.tokens,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub evictions: usize,
+    pub entries: usize,
+}
+
+/// Hash-consing table of green elements.
+///
+/// Children the policy interns are hashed and compared by pointer, because
+/// they were interned before their parent was built. Other children, like
+/// nodes wider than [`CachePolicy::max_node_width`], are compared structurally.
+#[derive(Debug, Default)]
+pub(crate) struct Interner {
+    buckets: HashMap<u64, Vec<Green>, BuildHasherDefault<Prehashed>>,
+    len: usize,
+    /// Misses since the last collection, see [`Interner::intern`].
+    misses: usize,
+}
+
+impl Interner {
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn hash(policy: &CachePolicy, data: &GreenData) -> u64 {
+        let mut state = DefaultHasher::new();
+        hash_data(policy, data, &mut state);
+        state.finish()
+    }
+
+    /// Returns the entry equal to `data`, or allocates it with `alloc` and
+    /// interns it while there is room for `limit` entries.
+    pub(crate) fn intern(
+        &mut self,
+        policy: &CachePolicy,
+        limit: Option<usize>,
+        hash: u64,
+        data: GreenData,
+        alloc: impl FnOnce(GreenData) -> Green,
+    ) -> Interned {
+        let full = limit.is_some_and(|limit| self.len >= limit);
+        let bucket = self.buckets.entry(hash).or_default();
+        if let Some(cached) = bucket
+            .iter()
+            .find(|green| same_data(policy, &green.0, &data))
+        {
+            return Interned::Hit(cached.clone());
+        }
+
+        let green = alloc(data);
+        self.misses += 1;
+        if !full {
+            bucket.push(green.clone());
+            self.len += 1;
+            return Interned::Miss { green, evicted: 0 };
+        }
+        // A collection visits every entry, so when the entries are still in
+        // use it is only retried after as many misses as there are entries.
+        let evicted = if self.misses >= self.len {
+            self.collect()
+        } else {
+            0
+        };
+        let room = limit.is_some_and(|limit| self.len < limit);
+        let bucket = self.buckets.entry(hash).or_default();
+        if room {
+            bucket.push(green.clone());
+            self.len += 1;
+        } else if bucket.is_empty() {
+            self.buckets.remove(&hash);
+        }
+        Interned::Miss { green, evicted }
+    }
+
+    /// Drops entries referenced only by the interner.
+    /// Returns the number of dropped entries.
+    pub(crate) fn collect(&mut self) -> usize {
+        self.misses = 0;
+        let mut dropped = 0;
+        // Dropping a parent releases its children, so repeat until nothing changes.
+        loop {
+            match self.sweep() {
+                0 => break dropped,
+                swept => dropped += swept,
             }
-            GreenKind::Token(_) | GreenKind::Alias(None) => unreachable!(),
         }
     }
 
-    pub fn alias<F, G>(&mut self, name: Name, f: F) -> Green
+    /// Drops entries referenced only by the interner, once.
+    /// Returns the number of dropped entries.
+    fn sweep(&mut self) -> usize {
+        let before = self.len;
+        self.buckets.retain(|_, bucket| {
+            bucket.retain(|green| green.0.strong_count() > 1);
+            !bucket.is_empty()
+        });
+        self.len = self.buckets.values().map(Vec::len).sum();
+        before - self.len
+    }
+}
+
+pub(crate) enum Interned {
+    Hit(Green),
+    Miss { green: Green, evicted: usize },
+}
+
+/// Keys of [`Interner::buckets`] are hashes already.
+#[derive(Default)]
+struct Prehashed(u64);
+
+impl Hasher for Prehashed {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = self.0.rotate_left(8) ^ u64::from(*byte);
+        }
+    }
+
+    fn write_u64(&mut self, hash: u64) {
+        self.0 = hash;
+    }
+}
+
+fn hash_data(policy: &CachePolicy, data: &GreenData, state: &mut DefaultHasher) {
+    data.name.hash(state);
+    data.size.hash(state);
+    match &data.kind {
+        GreenKind::Node(Node { children }) => {
+            0u8.hash(state);
+            children.len().hash(state);
+            for child in children {
+                hash_child(policy, child, state);
+            }
+        }
+        GreenKind::Alias(child) => {
+            1u8.hash(state);
+            child.is_some().hash(state);
+            if let Some(child) = child {
+                hash_child(policy, child, state);
+            }
+        }
+        GreenKind::Token(Token {
+            leading,
+            value,
+            trailing,
+        }) => {
+            2u8.hash(state);
+            leading.hash(state);
+            value.hash(state);
+            trailing.hash(state);
+        }
+    }
+}
+
+fn hash_child(policy: &CachePolicy, child: &Green, state: &mut DefaultHasher) {
+    if policy.allows(child.kind()) {
+        child.as_ptr().hash(state);
+    } else {
+        hash_data(policy, &child.0, state);
+    }
+}
+
+fn same_data(policy: &CachePolicy, left: &GreenData, right: &GreenData) -> bool {
+    if left.name != right.name || left.size != right.size {
+        return false;
+    }
+    match (&left.kind, &right.kind) {
+        (GreenKind::Node(left), GreenKind::Node(right)) => {
+            left.children.len() == right.children.len()
+                && left
+                    .children
+                    .iter()
+                    .zip(&right.children)
+                    .all(|(l, r)| same_child(policy, l, r))
+        }
+        (GreenKind::Alias(Some(left)), GreenKind::Alias(Some(right))) => {
+            same_child(policy, left, right)
+        }
+        (GreenKind::Alias(None), GreenKind::Alias(None)) => true,
+        (GreenKind::Token(left), GreenKind::Token(right)) => left == right,
+        _ => false,
+    }
+}
+
+fn same_child(policy: &CachePolicy, left: &Green, right: &Green) -> bool {
+    if policy.allows(left.kind()) {
+        left.ptr_eq(right)
+    } else {
+        left.ptr_eq(right) || same_data(policy, &left.0, &right.0)
+    }
+}
+
+impl Cache {
+    pub fn with_policy(policy: CachePolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// Allocates built elements in chunks owned by the cache instead of one by one.
+    ///
+    /// Trees are used like any other [`Green`] and may outlive the cache, but a
+    /// chunk is only freed when every element in it is dropped.
+    pub fn with_arena(policy: CachePolicy) -> Self {
+        Self {
+            policy,
+            arena: Some(Arena::default()),
+            ..Default::default()
+        }
+    }
+
+    pub fn policy(&self) -> &CachePolicy {
+        &self.policy
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.cache.len(),
+            ..self.stats
+        }
+    }
+
+    /// Drops every entry that is no longer referenced by a live tree.
+    /// Returns the number of dropped entries.
+    pub fn collect(&mut self) -> usize {
+        self.cache.collect()
+    }
+
+    pub fn node(
+        &mut self,
+        name: impl Into<Kind>,
+        f: impl FnOnce(&mut Self) -> Vec<Green>,
+    ) -> Green {
+        GreenBuilderExt::node(self, name, f)
+    }
+
+    pub fn replace_children(&mut self, green: Green, children: Vec<Green>) -> Green {
+        GreenBuilderExt::replace_children(self, green, children)
+    }
+
+    /// Rebuilds `green` with new children, keeping alias wrappers.
+    pub fn try_replace_children(
+        &mut self,
+        green: Green,
+        children: Vec<Green>,
+    ) -> Result<Green, MutationError> {
+        GreenBuilderExt::try_replace_children(self, green, children)
+    }
+
+    pub fn alias<F, G>(&mut self, name: impl Into<Kind>, f: F) -> Green
     where
         F: FnOnce(&mut Self) -> G,
         G: Into<Option<Green>>,
     {
-        let child = f(self).into();
-        let size = child.as_ref().map(|child| child.size()).unwrap_or_default();
-        self.add_node(GreenData {
-            name,
-            size,
-            kind: GreenKind::Alias(child),
-        })
+        GreenBuilderExt::alias(self, name, f)
     }
 
-    pub fn token(&mut self, name: Name, value: impl Into<SmolStr>) -> Green {
-        self.with_trivia(name, "", value.into(), "")
+    pub fn token(&mut self, name: impl Into<Kind>, value: impl Into<SmolStr>) -> Green {
+        GreenBuilderExt::token(self, name, value)
     }
 
     pub fn with_trivia(
         &mut self,
-        name: Name,
-        leading: impl Into<SmolStr>,
+        name: impl Into<Kind>,
+        leading: impl Into<Trivia>,
         value: impl Into<SmolStr>,
-        trailing: impl Into<SmolStr>,
+        trailing: impl Into<Trivia>,
     ) -> Green {
-        let leading = leading.into();
-        let value = value.into();
-        let trailing = trailing.into();
-        self.add_node(GreenData {
-            name,
-            size: value.len() + leading.len() + trailing.len(),
-            kind: GreenKind::Token(Token {
-                leading,
-                value,
-                trailing,
-            }),
-        })
+        GreenBuilderExt::with_trivia(self, name, leading, value, trailing)
     }
 }
 
@@ -92,21 +369,153 @@ impl Cache {
     }
 }
 
-impl Cache {
+impl GreenBuilder for Cache {
     fn add_node(&mut self, node: GreenData) -> Green {
-        match &node.kind {
-            GreenKind::Node(Node { children }) if children.len() >= 5 => Green(Arc::new(node)),
-            _ => {
-                let node = Green(Arc::new(node));
-                for cached in &self.cache {
-                    let c: &Green = &*cached;
-                    if c == &node {
-                        return cached.clone();
-                    }
-                }
-                self.cache.push(node.clone());
-                node
+        let arena = &mut self.arena;
+        let mut alloc = |node| match arena {
+            Some(arena) => Green(arena.alloc(node)),
+            None => Green::new(node),
+        };
+        if !self.policy.allows(&node.kind) {
+            return alloc(node);
+        }
+
+        let hash = Interner::hash(&self.policy, &node);
+        let limit = self.policy.max_entries;
+        match self.cache.intern(&self.policy, limit, hash, node, alloc) {
+            Interned::Hit(green) => {
+                self.stats.hits += 1;
+                green
+            }
+            Interned::Miss { green, evicted } => {
+                self.stats.misses += 1;
+                self.stats.evictions += evicted;
+                green
             }
         }
     }
 }
+
+/// Interns green elements for AST builders, mutations and the
+/// [`Editor`](crate::Editor).
+///
+/// Implemented by [`Cache`] and by shared references to a
+/// [`SharedCache`](crate::SharedCache), so both can be passed as
+/// `&mut dyn GreenBuilder`.
+pub trait GreenBuilder {
+    /// Returns an element equal to `node`, interned if the policy allows it.
+    fn add_node(&mut self, node: GreenData) -> Green;
+}
+
+/// Element constructors of every [`GreenBuilder`].
+///
+/// [`Cache`], [`SharedCache`](crate::SharedCache) and `dyn GreenBuilder` have
+/// the same methods built on this trait, so it is only needed in generic code.
+pub trait GreenBuilderExt: GreenBuilder {
+    fn node(&mut self, name: impl Into<Kind>, f: impl FnOnce(&mut Self) -> Vec<Green>) -> Green {
+        let name = name.into();
+        let children = f(self);
+        self.add_node(GreenData::node(name, children))
+    }
+
+    fn replace_children(&mut self, green: Green, children: Vec<Green>) -> Green {
+        replace_children(self, green, children).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Rebuilds `green` with new children, keeping alias wrappers.
+    fn try_replace_children(
+        &mut self,
+        green: Green,
+        children: Vec<Green>,
+    ) -> Result<Green, MutationError> {
+        replace_children(self, green, children)
+    }
+
+    fn alias<F, G>(&mut self, name: impl Into<Kind>, f: F) -> Green
+    where
+        F: FnOnce(&mut Self) -> G,
+        G: Into<Option<Green>>,
+    {
+        let name = name.into();
+        let child = f(self).into();
+        self.add_node(GreenData::alias(name, child))
+    }
+
+    fn token(&mut self, name: impl Into<Kind>, value: impl Into<SmolStr>) -> Green {
+        self.with_trivia(name, "", value.into(), "")
+    }
+
+    fn with_trivia(
+        &mut self,
+        name: impl Into<Kind>,
+        leading: impl Into<Trivia>,
+        value: impl Into<SmolStr>,
+        trailing: impl Into<Trivia>,
+    ) -> Green {
+        let data = GreenData::token(name.into(), leading.into(), value.into(), trailing.into());
+        self.add_node(data)
+    }
+}
+
+impl<B: GreenBuilder + ?Sized> GreenBuilderExt for B {}
+
+impl dyn GreenBuilder + '_ {
+    pub fn node(
+        &mut self,
+        name: impl Into<Kind>,
+        f: impl FnOnce(&mut Self) -> Vec<Green>,
+    ) -> Green {
+        GreenBuilderExt::node(self, name, f)
+    }
+
+    pub fn replace_children(&mut self, green: Green, children: Vec<Green>) -> Green {
+        GreenBuilderExt::replace_children(self, green, children)
+    }
+
+    /// Rebuilds `green` with new children, keeping alias wrappers.
+    pub fn try_replace_children(
+        &mut self,
+        green: Green,
+        children: Vec<Green>,
+    ) -> Result<Green, MutationError> {
+        GreenBuilderExt::try_replace_children(self, green, children)
+    }
+
+    pub fn alias<F, G>(&mut self, name: impl Into<Kind>, f: F) -> Green
+    where
+        F: FnOnce(&mut Self) -> G,
+        G: Into<Option<Green>>,
+    {
+        GreenBuilderExt::alias(self, name, f)
+    }
+
+    pub fn token(&mut self, name: impl Into<Kind>, value: impl Into<SmolStr>) -> Green {
+        GreenBuilderExt::token(self, name, value)
+    }
+
+    pub fn with_trivia(
+        &mut self,
+        name: impl Into<Kind>,
+        leading: impl Into<Trivia>,
+        value: impl Into<SmolStr>,
+        trailing: impl Into<Trivia>,
+    ) -> Green {
+        GreenBuilderExt::with_trivia(self, name, leading, value, trailing)
+    }
+}
+
+fn replace_children<B: GreenBuilder + ?Sized>(
+    builder: &mut B,
+    green: Green,
+    children: Vec<Green>,
+) -> Result<Green, MutationError> {
+    match green.kind() {
+        GreenKind::Node(_) => Ok(builder.add_node(GreenData::node(green.name(), children))),
+        GreenKind::Alias(Some(child)) => {
+            let child = replace_children(builder, child.clone(), children)?;
+            Ok(builder.add_node(GreenData::alias(green.name(), Some(child))))
+        }
+        GreenKind::Alias(None) => Err(MutationError::MissingAliasChild { name: green.name() }),
+        GreenKind::Token(_) => Err(MutationError::NotANode { name: green.name() }),
+    }
+}
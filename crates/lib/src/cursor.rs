@@ -0,0 +1,242 @@
+use text_size::{TextRange, TextSize};
+
+use crate::{Green, Kind, Red};
+
+/// Borrowing cursor over a red tree.
+///
+/// Moving the cursor does not allocate a `Red` per visited element, only the
+/// path below the starting `Red` is kept. Use [`RedCursor::red`] to get an
+/// owned `Red` for the current position.
+#[derive(Debug, Clone)]
+pub struct RedCursor<'a> {
+    anchor: &'a Red,
+    path: Vec<Frame<'a>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Frame<'a> {
+    green: &'a Green,
+    index: usize,
+    offset: TextSize,
+}
+
+fn children(green: &Green) -> &[Green] {
+    green
+        .as_node()
+        .map(|node| node.children.as_slice())
+        .unwrap_or_default()
+}
+
+impl Red {
+    pub fn cursor(&self) -> RedCursor<'_> {
+        RedCursor {
+            anchor: self,
+            path: vec![],
+        }
+    }
+}
+
+impl<'a> RedCursor<'a> {
+    pub fn green(&self) -> &'a Green {
+        match self.path.last() {
+            Some(frame) => frame.green,
+            None => self.anchor.green_ref(),
+        }
+    }
+
+    pub fn name(&self) -> Kind {
+        self.green().name()
+    }
+
+    pub fn is(&self, name: impl Into<Kind>) -> bool {
+        self.green().is(name)
+    }
+
+    pub fn is_node(&self) -> bool {
+        self.green().as_node().is_some()
+    }
+
+    pub fn offset(&self) -> TextSize {
+        match self.path.last() {
+            Some(frame) => frame.offset,
+            None => self.anchor.offset(),
+        }
+    }
+
+    pub fn text_range(&self) -> TextRange {
+        TextRange::at(self.offset(), self.green().text_len())
+    }
+
+    pub fn red(&self) -> Red {
+        self.path.iter().fold(self.anchor.clone(), |parent, frame| {
+            Red::child(frame.green.clone(), parent, frame.index, frame.offset)
+        })
+    }
+
+    pub fn goto_first_child(&mut self) -> bool {
+        let offset = self.offset();
+        match children(self.green()).first() {
+            Some(green) => {
+                self.path.push(Frame {
+                    green,
+                    index: 0,
+                    offset,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn goto_last_child(&mut self) -> bool {
+        let end = self.text_range().end();
+        let children = children(self.green());
+        match children.last() {
+            Some(green) => {
+                self.path.push(Frame {
+                    green,
+                    index: children.len() - 1,
+                    offset: end - green.text_len(),
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn goto_parent(&mut self) -> bool {
+        if self.path.pop().is_some() {
+            return true;
+        }
+        match self.anchor.kind().as_child() {
+            Some((parent, _, _)) => {
+                self.anchor = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn goto_next_sibling(&mut self) -> bool {
+        let (index, offset) = self.position();
+        let offset = offset + self.green().text_len();
+        self.goto_sibling(index + 1, |_| offset)
+    }
+
+    pub fn goto_prev_sibling(&mut self) -> bool {
+        let (index, offset) = match self.position() {
+            (0, _) => return false,
+            position => position,
+        };
+        self.goto_sibling(index - 1, |green| offset - green.text_len())
+    }
+
+    fn position(&self) -> (usize, TextSize) {
+        match self.path.last() {
+            Some(frame) => (frame.index, frame.offset),
+            None => self
+                .anchor
+                .kind()
+                .as_child()
+                .map(|(_, index, offset)| (index, offset))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn goto_sibling(&mut self, index: usize, offset: impl FnOnce(&Green) -> TextSize) -> bool {
+        let parent = match self.path.len() {
+            0 => match self.anchor.kind().as_child() {
+                Some((parent, _, _)) => parent.green_ref(),
+                None => return false,
+            },
+            1 => self.anchor.green_ref(),
+            len => self.path[len - 2].green,
+        };
+        let green = match children(parent).get(index) {
+            Some(green) => green,
+            None => return false,
+        };
+        let frame = Frame {
+            green,
+            index,
+            offset: offset(green),
+        };
+        match self.path.last_mut() {
+            Some(last) => *last = frame,
+            None => {
+                if let Some((parent, _, _)) = self.anchor.kind().as_child() {
+                    self.anchor = parent;
+                }
+                self.path.push(frame);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::tree;
+    use crate::*;
+
+    #[test]
+    fn walk() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+
+        let mut cursor = root.cursor();
+        let mut tokens = vec![];
+        'walk: loop {
+            if !cursor.is_node() {
+                tokens.push(format!("{} {:?}", cursor.name(), cursor.text_range()));
+            }
+            if cursor.goto_first_child() {
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    break 'walk;
+                }
+            }
+        }
+
+        assert_eq!(
+            vec![
+                "number 0..2",
+                "op 2..4",
+                "number 4..5",
+                "op 5..6",
+                "number 6..7",
+                "comma 7..8",
+                "number 8..9",
+                "number 9..10"
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn siblings_and_parent() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+        let value = root.first_child().unwrap();
+
+        let mut cursor = value.cursor();
+        assert!(cursor.goto_last_child());
+        assert_eq!(TextRange::new(4.into(), 7.into()), cursor.text_range());
+        assert!(cursor.goto_prev_sibling());
+        assert_eq!("op", cursor.name());
+        assert_eq!(
+            cursor.red(),
+            value.first_child().unwrap().next_sibling().unwrap()
+        );
+
+        assert!(cursor.goto_parent());
+        assert!(cursor.goto_next_sibling());
+        assert_eq!("comma", cursor.name());
+        assert!(cursor.goto_parent());
+        assert_eq!("Root", cursor.name());
+        assert!(!cursor.goto_parent());
+        assert!(!cursor.goto_prev_sibling());
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use smol_str::SmolStr;
+
+use crate::{Cache, Green, GreenData, GreenKind, Kind, Node, TextSize, Token, Trivia};
+
+/// Green tree as a DAG.
+///
+/// Entries are stored children first and refer to earlier entries by index,
+/// so every shared or structurally identical subtree is written only once.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Dag {
+    pub(crate) names: Vec<Kind>,
+    pub(crate) entries: Vec<Entry>,
+    pub(crate) root: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum Entry {
+    Node {
+        name: u32,
+        children: Vec<u32>,
+    },
+    Alias {
+        name: u32,
+        child: Option<u32>,
+    },
+    Token {
+        name: u32,
+        leading: Trivia,
+        value: SmolStr,
+        trailing: Trivia,
+    },
+}
+
+#[derive(Default)]
+struct Encoder {
+    names: Vec<Kind>,
+    name_ids: HashMap<Kind, u32>,
+    entries: Vec<Entry>,
+    entry_ids: HashMap<Entry, u32>,
+    visited: HashMap<*const GreenData, u32>,
+}
+
+impl Encoder {
+    fn encode(green: &Green) -> Dag {
+        let mut encoder = Self::default();
+        let root = encoder.green(green);
+        Dag {
+            names: encoder.names,
+            entries: encoder.entries,
+            root,
+        }
+    }
+
+    fn name(&mut self, name: Kind) -> u32 {
+        let names = &mut self.names;
+        *self.name_ids.entry(name).or_insert_with(|| {
+            names.push(name);
+            names.len() as u32 - 1
+        })
+    }
+
+    fn green(&mut self, green: &Green) -> u32 {
+        if let Some(id) = self.visited.get(&green.as_ptr()) {
+            return *id;
+        }
+        let name = self.name(green.name());
+        let entry = match green.kind() {
+            GreenKind::Node(Node { children }) => Entry::Node {
+                name,
+                children: children.iter().map(|child| self.green(child)).collect(),
+            },
+            GreenKind::Alias(child) => Entry::Alias {
+                name,
+                child: child.as_ref().map(|child| self.green(child)),
+            },
+            GreenKind::Token(Token {
+                leading,
+                value,
+                trailing,
+            }) => Entry::Token {
+                name,
+                leading: leading.clone(),
+                value: value.clone(),
+                trailing: trailing.clone(),
+            },
+        };
+        let entries = &mut self.entries;
+        let id = *self.entry_ids.entry(entry).or_insert_with_key(|entry| {
+            entries.push(entry.clone());
+            entries.len() as u32 - 1
+        });
+        self.visited.insert(green.as_ptr(), id);
+        id
+    }
+}
+
+impl Dag {
+    pub(crate) fn encode(green: &Green) -> Self {
+        Encoder::encode(green)
+    }
+
+    /// Rebuilds the tree through `builder`, one green per entry.
+    #[cfg(feature = "serde")]
+    pub(crate) fn decode(self, builder: &mut Cache) -> Result<Green, String> {
+        let Dag {
+            names,
+            entries,
+            root,
+        } = self;
+        let mut decoder = Decoder::new(builder, names, entries.len());
+        for entry in entries {
+            match entry {
+                Entry::Node { name, children } => {
+                    let children = children
+                        .into_iter()
+                        .map(|id| decoder.child(id))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    decoder.node(name, children)?
+                }
+                Entry::Alias { name, child } => {
+                    let child = child.map(|id| decoder.child(id)).transpose()?;
+                    decoder.alias(name, child)?
+                }
+                Entry::Token {
+                    name,
+                    leading,
+                    value,
+                    trailing,
+                } => decoder.token(name, leading, value, trailing)?,
+            }
+        }
+        decoder.finish(root)
+    }
+}
+
+/// Builds greens of entries in order, checking references and text sizes.
+pub(crate) struct Decoder<'a> {
+    builder: &'a mut Cache,
+    names: Vec<Kind>,
+    decoded: Vec<Green>,
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(builder: &'a mut Cache, names: Vec<Kind>, entries: usize) -> Self {
+        Self {
+            builder,
+            names,
+            decoded: Vec::with_capacity(entries),
+        }
+    }
+
+    fn name(&self, id: u32) -> Result<Kind, String> {
+        self.names
+            .get(id as usize)
+            .copied()
+            .ok_or_else(|| format!("Unknown name {}", id))
+    }
+
+    pub(crate) fn child(&self, id: u32) -> Result<Green, String> {
+        self.decoded
+            .get(id as usize)
+            .cloned()
+            .ok_or_else(|| format!("Entry {} is not a back-reference", id))
+    }
+
+    pub(crate) fn node(&mut self, name: u32, children: Vec<Green>) -> Result<(), String> {
+        let name = self.name(name)?;
+        children
+            .iter()
+            .try_fold(TextSize::default(), |size, child| {
+                size.checked_add(child.text_len())
+            })
+            .ok_or_else(too_large)?;
+        let green = self.builder.node(name, |_| children);
+        self.decoded.push(green);
+        Ok(())
+    }
+
+    pub(crate) fn alias(&mut self, name: u32, child: Option<Green>) -> Result<(), String> {
+        let name = self.name(name)?;
+        let green = self.builder.alias(name, |_| child);
+        self.decoded.push(green);
+        Ok(())
+    }
+
+    pub(crate) fn token(
+        &mut self,
+        name: u32,
+        leading: Trivia,
+        value: impl Into<SmolStr>,
+        trailing: Trivia,
+    ) -> Result<(), String> {
+        let name = self.name(name)?;
+        let value = value.into();
+        leading
+            .pieces()
+            .iter()
+            .map(|piece| piece.text.as_str())
+            .chain(Some(value.as_str()))
+            .chain(trailing.pieces().iter().map(|piece| piece.text.as_str()))
+            .try_fold(TextSize::default(), |size, text| {
+                size.checked_add(TextSize::try_from(text.len()).ok()?)
+            })
+            .ok_or_else(too_large)?;
+        let green = self.builder.with_trivia(name, leading, value, trailing);
+        self.decoded.push(green);
+        Ok(())
+    }
+
+    pub(crate) fn finish(self, root: u32) -> Result<Green, String> {
+        self.decoded
+            .get(root as usize)
+            .cloned()
+            .ok_or_else(|| format!("Unknown root {}", root))
+    }
+}
+
+fn too_large() -> String {
+    "Text does not fit `TextSize`".into()
+}
@@ -0,0 +1,422 @@
+use std::fmt::{self, Debug, Display, Formatter};
+
+use text_size::{TextRange, TextSize};
+
+use crate::{Green, GreenKind, Node, Red, Token, Trivia};
+
+/// Layout of `Debug` output for [`Green`] and [`Red`].
+///
+/// `{:?}` prints with the defaults, for anything else use [`Green::debug`]
+/// or [`Red::debug`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugOptions {
+    /// Show leading and trailing trivia of tokens.
+    pub trivia: bool,
+    pub aliases: AliasStyle,
+    /// Deeper children are replaced with `...`.
+    pub max_depth: Option<usize>,
+    pub offsets: OffsetUnit,
+    /// Everything on a single line.
+    pub compact: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AliasStyle {
+    /// `Value, Array @ 0..2` on one line.
+    Collapsed,
+    /// Alias on its own line, with the aliased element as its child.
+    Expanded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffsetUnit {
+    Bytes,
+    Chars,
+}
+
+impl Default for DebugOptions {
+    fn default() -> Self {
+        Self {
+            trivia: true,
+            aliases: AliasStyle::Collapsed,
+            max_depth: None,
+            offsets: OffsetUnit::Bytes,
+            compact: false,
+        }
+    }
+}
+
+impl DebugOptions {
+    pub fn with_trivia(mut self, trivia: bool) -> Self {
+        self.trivia = trivia;
+        self
+    }
+
+    pub fn with_aliases(mut self, aliases: AliasStyle) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    pub fn with_max_depth(mut self, depth: impl Into<Option<usize>>) -> Self {
+        self.max_depth = depth.into();
+        self
+    }
+
+    pub fn with_offsets(mut self, offsets: OffsetUnit) -> Self {
+        self.offsets = offsets;
+        self
+    }
+
+    pub fn with_compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+}
+
+/// Tree printed with [`DebugOptions`], returned by [`Green::debug`] and [`Red::debug`].
+pub struct DebugTree<'a> {
+    green: &'a Green,
+    offset: TextSize,
+    /// Characters before `offset`, only needed for [`OffsetUnit::Chars`].
+    chars: usize,
+    options: DebugOptions,
+}
+
+impl Green {
+    pub fn debug(&self, options: DebugOptions) -> DebugTree<'_> {
+        DebugTree {
+            green: self,
+            offset: 0.into(),
+            chars: 0,
+            options,
+        }
+    }
+}
+
+impl Red {
+    /// Prints the subtree of this element at its position in the root.
+    pub fn debug(&self, options: DebugOptions) -> DebugTree<'_> {
+        let chars = match options.offsets {
+            OffsetUnit::Bytes => 0,
+            // Only the siblings before each ancestor are counted.
+            OffsetUnit::Chars => self
+                .ancestors()
+                .filter_map(|red| {
+                    let (parent, index, _) = red.kind().as_child()?;
+                    Some(
+                        parent
+                            .green()
+                            .children()
+                            .take(index)
+                            .map(|c| char_len(&c))
+                            .sum::<usize>(),
+                    )
+                })
+                .sum(),
+        };
+        DebugTree {
+            green: self.green_ref(),
+            offset: self.offset(),
+            chars,
+            options,
+        }
+    }
+}
+
+impl Debug for Green {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.debug(DebugOptions::default()), f)
+    }
+}
+
+impl Debug for Red {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.debug(DebugOptions::default()), f)
+    }
+}
+
+impl Debug for DebugTree<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Display for DebugTree<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut printer = Printer {
+            f,
+            options: &self.options,
+        };
+        let indent = printer.f.width().unwrap_or_default();
+        if !self.options.compact {
+            write!(printer.f, "{:indent$}", "", indent = indent)?;
+        }
+        let position = Position {
+            bytes: self.offset,
+            chars: self.chars,
+        };
+        printer.element(self.green, position, 0, indent)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Position {
+    bytes: TextSize,
+    chars: usize,
+}
+
+impl Position {
+    fn advance(&mut self, green: &Green, unit: OffsetUnit) {
+        self.bytes += green.text_len();
+        if unit == OffsetUnit::Chars {
+            self.chars += char_len(green);
+        }
+    }
+}
+
+fn char_len(green: &Green) -> usize {
+    match green.kind() {
+        GreenKind::Node(Node { children }) => children.iter().map(char_len).sum(),
+        GreenKind::Alias(child) => child.as_ref().map(char_len).unwrap_or_default(),
+        GreenKind::Token(Token {
+            leading,
+            value,
+            trailing,
+        }) => trivia_char_len(leading) + value.chars().count() + trivia_char_len(trailing),
+    }
+}
+
+fn trivia_char_len(trivia: &Trivia) -> usize {
+    trivia
+        .pieces()
+        .iter()
+        .map(|piece| piece.text.chars().count())
+        .sum()
+}
+
+struct Printer<'a, 'f> {
+    f: &'a mut Formatter<'f>,
+    options: &'a DebugOptions,
+}
+
+impl Printer<'_, '_> {
+    fn element(
+        &mut self,
+        green: &Green,
+        position: Position,
+        depth: usize,
+        indent: usize,
+    ) -> fmt::Result {
+        let mut green = green;
+        if self.options.aliases == AliasStyle::Collapsed {
+            while let GreenKind::Alias(child) = green.kind() {
+                write!(self.f, "{}, ", green.name())?;
+                match child {
+                    Some(child) => green = child,
+                    None => return write!(self.f, "missing"),
+                }
+            }
+        }
+
+        write!(self.f, "{} @ ", green.name())?;
+        self.range(green, position)?;
+
+        match green.kind() {
+            GreenKind::Node(Node { children }) => self.children(children, position, depth, indent),
+            GreenKind::Alias(Some(child)) => {
+                self.children(std::slice::from_ref(child), position, depth, indent)
+            }
+            GreenKind::Alias(None) => write!(self.f, " missing"),
+            GreenKind::Token(token) => self.token(token),
+        }
+    }
+
+    fn range(&mut self, green: &Green, position: Position) -> fmt::Result {
+        match self.options.offsets {
+            OffsetUnit::Bytes => write!(
+                self.f,
+                "{:?}",
+                TextRange::at(position.bytes, green.text_len())
+            ),
+            OffsetUnit::Chars => write!(
+                self.f,
+                "{}..{}",
+                position.chars,
+                position.chars + char_len(green)
+            ),
+        }
+    }
+
+    fn children(
+        &mut self,
+        children: &[Green],
+        mut position: Position,
+        depth: usize,
+        indent: usize,
+    ) -> fmt::Result {
+        let truncated = !children.is_empty()
+            && self
+                .options
+                .max_depth
+                .is_some_and(|max_depth| depth >= max_depth);
+
+        if self.options.compact {
+            write!(self.f, " [")?;
+            if truncated {
+                return write!(self.f, "...]");
+            }
+            for (idx, child) in children.iter().enumerate() {
+                if idx > 0 {
+                    write!(self.f, ", ")?;
+                }
+                self.element(child, position, depth + 1, indent)?;
+                position.advance(child, self.options.offsets);
+            }
+            return write!(self.f, "]");
+        }
+
+        let indent = indent + 4;
+        if truncated {
+            return write!(self.f, "\n{:indent$}...", "", indent = indent);
+        }
+        for child in children {
+            write!(self.f, "\n{:indent$}", "", indent = indent)?;
+            self.element(child, position, depth + 1, indent)?;
+            position.advance(child, self.options.offsets);
+        }
+        Ok(())
+    }
+
+    fn token(&mut self, token: &Token) -> fmt::Result {
+        if self.options.compact {
+            write!(self.f, " {:?}", token.value.as_str())?;
+        } else {
+            write!(self.f, " `{}`", escape(&token.value))?;
+        }
+        if self.options.trivia {
+            self.trivia("leading", &token.leading)?;
+            self.trivia("trailing", &token.trailing)?;
+        }
+        Ok(())
+    }
+
+    fn trivia(&mut self, label: &str, trivia: &Trivia) -> fmt::Result {
+        if trivia.is_empty() {
+            return Ok(());
+        }
+        if self.options.compact {
+            write!(self.f, " {}: {:?}", label, trivia.to_string())
+        } else {
+            write!(self.f, " ; {}: `{}`", label, escape(&trivia.to_string()))
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\t', "\\t").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::tree;
+    use crate::*;
+
+    #[test]
+    fn tree_layouts() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+
+        assert_eq!(
+            "Root @ 0..10
+    Value, Add @ 0..7
+        number @ 0..2 `1` ; trailing: ` `
+        op @ 2..4 `+` ; trailing: ` `
+        Add @ 4..7
+            number @ 4..5 `2`
+            op @ 5..6 `+`
+            number @ 6..7 `3`
+    comma @ 7..8 `,`
+    List @ 8..10
+        number @ 8..9 `4`
+        number @ 9..10 `5`",
+            format!("{:?}", root.green())
+        );
+        assert_eq!(
+            format!("{:?}", root.green()),
+            format!("{:#?}", root.green())
+        );
+
+        let options = DebugOptions::default()
+            .with_trivia(false)
+            .with_aliases(AliasStyle::Expanded)
+            .with_max_depth(2);
+        assert_eq!(
+            "Root @ 0..10
+    Value @ 0..7
+        Add @ 0..7
+            ...
+    comma @ 7..8 `,`
+    List @ 8..10
+        number @ 8..9 `4`
+        number @ 9..10 `5`",
+            root.green().debug(options).to_string()
+        );
+    }
+
+    #[test]
+    fn compact_and_red() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+
+        assert_eq!(
+            r#"Root @ 0..10 [Value, Add @ 0..7 [number @ 0..2 "1" trailing: " ", op @ 2..4 "+" trailing: " ", Add @ 4..7 [number @ 4..5 "2", op @ 5..6 "+", number @ 6..7 "3"]], comma @ 7..8 ",", List @ 8..10 [number @ 8..9 "4", number @ 9..10 "5"]]"#,
+            root.green()
+                .debug(DebugOptions::default().with_compact(true))
+                .to_string()
+        );
+
+        let value = root.first_child().unwrap();
+        let op = value.children().nth(1).unwrap();
+        assert_eq!("op @ 2..4 `+` ; trailing: ` `", format!("{:?}", op));
+        assert_eq!(
+            "Value, Add @ 0..7 [...]",
+            format!(
+                "{:?}",
+                value.debug(DebugOptions::default().with_compact(true).with_max_depth(0))
+            )
+        );
+    }
+
+    #[test]
+    fn char_offsets() {
+        let mut builder = Cache::default();
+        let root = Red::root(builder.node("Root", |builder| {
+            vec![
+                builder.with_trivia("string", "\n", "\"ł\"", ""),
+                builder.alias("Value", |_| None),
+                builder.token("number", "2"),
+            ]
+        }));
+        let string = root.first_child().unwrap();
+        let missing = string.next_sibling().unwrap();
+        let number = root.last_child().unwrap();
+
+        let options = DebugOptions::default().with_offsets(OffsetUnit::Chars);
+        assert_eq!(
+            "  string @ 0..4 `\"ł\"` ; leading: `\\n`",
+            format!("{:2}", string.debug(options.clone()))
+        );
+        assert_eq!("Value, missing", missing.debug(options.clone()).to_string());
+        assert_eq!(
+            "number @ 4..5 `2`",
+            number.debug(options.clone()).to_string()
+        );
+        assert_eq!(
+            "Value @ 4..4 missing",
+            missing
+                .debug(options.with_aliases(AliasStyle::Expanded))
+                .to_string()
+        );
+        assert_eq!("number @ 5..6 `2`", format!("{:?}", number));
+    }
+}
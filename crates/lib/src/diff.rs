@@ -0,0 +1,507 @@
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::{Index, IndexMut, Range};
+
+use text_size::{TextRange, TextSize};
+
+use crate::{Green, GreenKind, Node, Token};
+
+/// Single difference between two green trees.
+///
+/// Paths and ranges always point into the old tree. Paths are child indices
+/// starting from the root, with aliases being transparent like in [`Red`](crate::Red).
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffOp {
+    /// `green` is inserted into `parent` before the old child at `index`.
+    Insert {
+        parent: Vec<usize>,
+        index: usize,
+        offset: TextSize,
+        green: Green,
+    },
+    Delete {
+        path: Vec<usize>,
+        range: TextRange,
+        green: Green,
+    },
+    Replace {
+        path: Vec<usize>,
+        range: TextRange,
+        old: Green,
+        new: Green,
+    },
+}
+
+/// Structural diff of two trees.
+///
+/// Subtrees shared by pointer, e.g. thanks to [`Cache`](crate::Cache), are
+/// skipped without being visited. Other children are matched structurally.
+pub fn diff(old: &Green, new: &Green) -> Vec<DiffOp> {
+    let mut ops = vec![];
+    diff_green(&mut ops, &mut vec![], 0.into(), old, new);
+    ops
+}
+
+fn diff_green(
+    ops: &mut Vec<DiffOp>,
+    path: &mut Vec<usize>,
+    offset: TextSize,
+    old: &Green,
+    new: &Green,
+) {
+    if old.ptr_eq(new) {
+        return;
+    }
+    match (old.kind(), new.kind()) {
+        (GreenKind::Alias(Some(old_child)), GreenKind::Alias(Some(new_child)))
+            if old.name() == new.name() =>
+        {
+            diff_green(ops, path, offset, old_child, new_child)
+        }
+        (GreenKind::Node(old_node), GreenKind::Node(new_node)) if old.name() == new.name() => {
+            diff_children(ops, path, offset, &old_node.children, &new_node.children)
+        }
+        _ if old == new => (),
+        _ => ops.push(DiffOp::Replace {
+            path: path.clone(),
+            range: TextRange::at(offset, old.text_len()),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+    }
+}
+
+fn diff_children(
+    ops: &mut Vec<DiffOp>,
+    path: &mut Vec<usize>,
+    offset: TextSize,
+    old: &[Green],
+    new: &[Green],
+) {
+    let mut offsets = Vec::with_capacity(old.len() + 1);
+    offsets.push(offset);
+    for child in old {
+        offsets.push(offsets[offsets.len() - 1] + child.text_len());
+    }
+
+    let mut gap = Gap::default();
+    for (i, j) in Myers::new(old, new).matches() {
+        gap.deleted = i - gap.old;
+        gap.inserted = j - gap.new;
+        gap.flush(ops, path, &offsets, old, new);
+        gap.old = i + 1;
+        gap.new = j + 1;
+    }
+    gap.deleted = old.len() - gap.old;
+    gap.inserted = new.len() - gap.new;
+    gap.flush(ops, path, &offsets, old, new);
+}
+
+/// Children list with lazily computed structural hashes.
+struct Side<'a> {
+    greens: &'a [Green],
+    hashes: Vec<Cell<Option<u64>>>,
+}
+
+impl<'a> Side<'a> {
+    fn new(greens: &'a [Green]) -> Self {
+        Self {
+            greens,
+            hashes: vec![Cell::new(None); greens.len()],
+        }
+    }
+
+    fn hash(&self, idx: usize) -> u64 {
+        let cell = &self.hashes[idx];
+        cell.get().unwrap_or_else(|| {
+            let mut state = DefaultHasher::new();
+            hash_green(&self.greens[idx], &mut state);
+            let hash = state.finish();
+            cell.set(Some(hash));
+            hash
+        })
+    }
+}
+
+fn hash_green(green: &Green, state: &mut DefaultHasher) {
+    green.name().hash(state);
+    green.text_len().hash(state);
+    match green.kind() {
+        GreenKind::Node(Node { children }) => {
+            0u8.hash(state);
+            children.len().hash(state);
+            children.iter().for_each(|child| hash_green(child, state));
+        }
+        GreenKind::Alias(child) => {
+            1u8.hash(state);
+            child.is_some().hash(state);
+            child.iter().for_each(|child| hash_green(child, state));
+        }
+        GreenKind::Token(Token {
+            leading,
+            value,
+            trailing,
+        }) => {
+            2u8.hash(state);
+            leading.hash(state);
+            value.hash(state);
+            trailing.hash(state);
+        }
+    }
+}
+
+/// Linear space variant of Myers' diff, matching children shared by pointer
+/// or structurally equal, so trees from different caches diff well too.
+struct Myers<'a> {
+    old: Side<'a>,
+    new: Side<'a>,
+    forward: Diagonals,
+    backward: Diagonals,
+    matches: Vec<(usize, usize)>,
+}
+
+impl<'a> Myers<'a> {
+    fn new(old: &'a [Green], new: &'a [Green]) -> Self {
+        let max_d = max_d(old.len(), new.len());
+        Self {
+            old: Side::new(old),
+            new: Side::new(new),
+            forward: Diagonals::new(max_d),
+            backward: Diagonals::new(max_d),
+            matches: vec![],
+        }
+    }
+
+    /// Pairs of matched old and new indices, in order.
+    fn matches(mut self) -> Vec<(usize, usize)> {
+        self.conquer(0..self.old.greens.len(), 0..self.new.greens.len());
+        self.matches
+    }
+
+    fn same(&self, i: usize, j: usize) -> bool {
+        let (old, new) = (&self.old.greens[i], &self.new.greens[j]);
+        old.ptr_eq(new) || (self.old.hash(i) == self.new.hash(j) && old == new)
+    }
+
+    fn prefix(&self, old: Range<usize>, new: Range<usize>) -> usize {
+        old.zip(new).take_while(|(i, j)| self.same(*i, *j)).count()
+    }
+
+    fn suffix(&self, old: Range<usize>, new: Range<usize>) -> usize {
+        old.rev()
+            .zip(new.rev())
+            .take_while(|(i, j)| self.same(*i, *j))
+            .count()
+    }
+
+    fn conquer(&mut self, mut old: Range<usize>, mut new: Range<usize>) {
+        let prefix = self.prefix(old.clone(), new.clone());
+        self.matches
+            .extend((0..prefix).map(|k| (old.start + k, new.start + k)));
+        old.start += prefix;
+        new.start += prefix;
+
+        let suffix = self.suffix(old.clone(), new.clone());
+        old.end -= suffix;
+        new.end -= suffix;
+
+        if !old.is_empty() && !new.is_empty() {
+            if let Some((x, y)) = self.middle_snake(old.clone(), new.clone()) {
+                self.conquer(old.start..x, new.start..y);
+                self.conquer(x..old.end, y..new.end);
+            }
+        }
+
+        self.matches
+            .extend((0..suffix).map(|k| (old.end + k, new.end + k)));
+    }
+
+    /// Start of the middle snake of an optimal edit script.
+    fn middle_snake(&mut self, old: Range<usize>, new: Range<usize>) -> Option<(usize, usize)> {
+        let (n, m) = (old.len(), new.len());
+        let delta = n as isize - m as isize;
+        let odd = delta & 1 == 1;
+        self.forward[1] = 0;
+        self.backward[1] = 0;
+
+        for d in 0..max_d(n, m) as isize {
+            for k in (-d..=d).rev().step_by(2) {
+                let mut x = if k == -d || (k != d && self.forward[k - 1] < self.forward[k + 1]) {
+                    self.forward[k + 1]
+                } else {
+                    self.forward[k - 1] + 1
+                };
+                let y = (x as isize - k) as usize;
+                let (x0, y0) = (x, y);
+                if x < n && y < m {
+                    x += self.prefix(old.start + x..old.end, new.start + y..new.end);
+                }
+                self.forward[k] = x;
+                if odd
+                    && (k - delta).abs() < d
+                    && self.forward[k] + self.backward[-(k - delta)] >= n
+                {
+                    return Some((old.start + x0, new.start + y0));
+                }
+            }
+
+            for k in (-d..=d).rev().step_by(2) {
+                let mut x = if k == -d || (k != d && self.backward[k - 1] < self.backward[k + 1]) {
+                    self.backward[k + 1]
+                } else {
+                    self.backward[k - 1] + 1
+                };
+                let mut y = (x as isize - k) as usize;
+                if x < n && y < m {
+                    let advance =
+                        self.suffix(old.start..old.start + n - x, new.start..new.start + m - y);
+                    x += advance;
+                    y += advance;
+                }
+                self.backward[k] = x;
+                if !odd
+                    && (k - delta).abs() <= d
+                    && self.backward[k] + self.forward[-(k - delta)] >= n
+                {
+                    return Some((old.start + n - x, new.start + m - y));
+                }
+            }
+        }
+        None
+    }
+}
+
+fn max_d(old: usize, new: usize) -> usize {
+    (old + new).div_ceil(2) + 1
+}
+
+/// Furthest reaching x for every diagonal `k`, indexed from `-max_d`.
+struct Diagonals {
+    offset: isize,
+    xs: Vec<usize>,
+}
+
+impl Diagonals {
+    fn new(max_d: usize) -> Self {
+        Self {
+            offset: max_d as isize,
+            xs: vec![0; 2 * max_d],
+        }
+    }
+}
+
+impl Index<isize> for Diagonals {
+    type Output = usize;
+
+    fn index(&self, k: isize) -> &usize {
+        &self.xs[(k + self.offset) as usize]
+    }
+}
+
+impl IndexMut<isize> for Diagonals {
+    fn index_mut(&mut self, k: isize) -> &mut usize {
+        &mut self.xs[(k + self.offset) as usize]
+    }
+}
+
+/// Run of unmatched children, starting at `old` in the old and `new` in the new list.
+#[derive(Default)]
+struct Gap {
+    old: usize,
+    new: usize,
+    deleted: usize,
+    inserted: usize,
+}
+
+impl Gap {
+    fn flush(
+        &mut self,
+        ops: &mut Vec<DiffOp>,
+        path: &mut Vec<usize>,
+        offsets: &[TextSize],
+        old: &[Green],
+        new: &[Green],
+    ) {
+        let paired = std::cmp::min(self.deleted, self.inserted);
+        for k in 0..paired {
+            let idx = self.old + k;
+            path.push(idx);
+            diff_green(ops, path, offsets[idx], &old[idx], &new[self.new + k]);
+            path.pop();
+        }
+        for idx in self.old + paired..self.old + self.deleted {
+            path.push(idx);
+            ops.push(DiffOp::Delete {
+                path: path.clone(),
+                range: TextRange::new(offsets[idx], offsets[idx + 1]),
+                green: old[idx].clone(),
+            });
+            path.pop();
+        }
+        let index = self.old + self.deleted;
+        for green in &new[self.new + paired..self.new + self.inserted] {
+            ops.push(DiffOp::Insert {
+                parent: path.clone(),
+                index,
+                offset: offsets[index],
+                green: green.clone(),
+            });
+        }
+        self.old += self.deleted;
+        self.new += self.inserted;
+        self.deleted = 0;
+        self.inserted = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn list(builder: &mut Cache, items: &[&str]) -> Green {
+        builder.node("Root", |builder| {
+            items
+                .iter()
+                .map(|item| match item.parse::<u32>() {
+                    Ok(_) => builder.node("Add", |builder| {
+                        vec![
+                            builder.token("number", *item),
+                            builder.token("op", "+"),
+                            builder.token("number", "1"),
+                        ]
+                    }),
+                    Err(_) => builder.token("comma", *item),
+                })
+                .collect()
+        })
+    }
+
+    fn apply(builder: &mut Cache, old: &Green, ops: &[DiffOp]) -> Green {
+        let root = Red::root(old.clone());
+        let find = |path: &[usize]| {
+            path.iter()
+                .try_fold(root.clone(), |red, idx| red.nth_child(*idx))
+                .unwrap()
+        };
+        let mut editor = Editor::new(root.clone());
+        for op in ops {
+            match op {
+                DiffOp::Insert {
+                    parent,
+                    index,
+                    green,
+                    ..
+                } => editor.insert_many(&find(parent), *index, vec![green.clone()]),
+                DiffOp::Delete { path, .. } => editor.remove(&find(path)),
+                DiffOp::Replace { path, new, .. } => editor.replace(&find(path), new.clone()),
+            }
+            .unwrap();
+        }
+        editor.finish(builder).green()
+    }
+
+    #[test]
+    fn same_tree() {
+        let mut builder = Cache::default();
+        let old = list(&mut builder, &["1", ",", "2"]);
+        let new = list(&mut builder, &["1", ",", "2"]);
+
+        assert_eq!(Vec::<DiffOp>::new(), diff(&old, &new));
+    }
+
+    #[test]
+    fn nested_replace() {
+        let mut builder = Cache::default();
+        let old = list(&mut builder, &["1", ",", "2"]);
+        let new = list(&mut builder, &["1", ",", "3"]);
+
+        let ops = diff(&old, &new);
+
+        assert_eq!(
+            vec![DiffOp::Replace {
+                path: vec![2, 0],
+                range: TextRange::new(4.into(), 5.into()),
+                old: builder.token("number", "2"),
+                new: builder.token("number", "3"),
+            }],
+            ops
+        );
+        assert_eq!(new, apply(&mut builder, &old, &ops));
+    }
+
+    #[test]
+    fn insert_and_delete() {
+        let mut builder = Cache::default();
+        let old = list(&mut builder, &["1", ",", "2", ";", "3"]);
+        let new = list(&mut builder, &["0", ",", "1", ",", "3"]);
+
+        let ops = diff(&old, &new);
+
+        assert_eq!(
+            vec![
+                DiffOp::Insert {
+                    parent: vec![],
+                    index: 0,
+                    offset: 0.into(),
+                    green: new.children().next().unwrap(),
+                },
+                DiffOp::Insert {
+                    parent: vec![],
+                    index: 0,
+                    offset: 0.into(),
+                    green: builder.token("comma", ","),
+                },
+                DiffOp::Delete {
+                    path: vec![2],
+                    range: TextRange::new(4.into(), 7.into()),
+                    green: old.children().nth(2).unwrap(),
+                },
+                DiffOp::Delete {
+                    path: vec![3],
+                    range: TextRange::new(7.into(), 8.into()),
+                    green: builder.token("comma", ";"),
+                },
+            ],
+            ops
+        );
+        assert_eq!(new.to_string(), apply(&mut builder, &old, &ops).to_string());
+    }
+
+    #[test]
+    fn insert_across_caches() {
+        let items = ["1", "2", "3", "4", "5", "6", "7", "8"];
+        let old = list(&mut Cache::default(), &items);
+        let mut builder = Cache::default();
+        let new = list(&mut builder, &[&["0"], &items[..]].concat());
+
+        assert_eq!(
+            vec![DiffOp::Insert {
+                parent: vec![],
+                index: 0,
+                offset: 0.into(),
+                green: new.children().next().unwrap(),
+            }],
+            diff(&old, &new)
+        );
+    }
+
+    #[test]
+    fn applied_diff_gives_new_tree() {
+        let cases: &[(&[&str], &[&str])] = &[
+            (&["1", ",", "2", ",", "3"], &["3", ",", "2", ",", "1"]),
+            (&["1", "2", "3", "4", "5", "6"], &["2", "4", "6", "7"]),
+            (&[",", ",", "1", ";", "2", ";"], &[";", "1", ",", ",", "2"]),
+            (&[], &["1", ",", "2"]),
+            (&["1", ",", "2"], &[]),
+        ];
+        for (old, new) in cases {
+            let mut builder = Cache::default();
+            let old = list(&mut builder, old);
+            let new = list(&mut Cache::with_policy(CachePolicy::disabled()), new);
+
+            let ops = diff(&old, &new);
+            assert_eq!(new.to_string(), apply(&mut builder, &old, &ops).to_string());
+        }
+    }
+}
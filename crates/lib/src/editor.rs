@@ -0,0 +1,356 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Green, GreenBuilder, MutationError, Red};
+
+/// Collects edits keyed by nodes of one tree and applies them in a single rebuild.
+///
+/// Unlike [`GreenMutate`](crate::GreenMutate), recorded `Red`s stay valid until
+/// [`Editor::finish`], so several edits can be made without re-finding nodes.
+#[derive(Debug)]
+pub struct Editor {
+    root: Red,
+    edits: EditNode,
+}
+
+#[derive(Debug, Default)]
+struct EditNode {
+    /// `Some(None)` removes the node.
+    replace: Option<Option<Green>>,
+    before: Vec<Green>,
+    after: Vec<Green>,
+    /// Inserted before the child with the given index, or at the end.
+    inserts: BTreeMap<usize, Vec<Green>>,
+    children: BTreeMap<usize, EditNode>,
+}
+
+impl Editor {
+    pub fn new(root: Red) -> Self {
+        let root = root.ancestors().last().unwrap_or(root);
+        Self {
+            root,
+            edits: Default::default(),
+        }
+    }
+
+    pub fn root(&self) -> &Red {
+        &self.root
+    }
+
+    pub fn replace(&mut self, red: &Red, green: Green) -> Result<(), MutationError> {
+        self.edit(red)?.replace = Some(Some(green));
+        Ok(())
+    }
+
+    pub fn remove(&mut self, red: &Red) -> Result<(), MutationError> {
+        Self::has_parent(red)?;
+        self.edit(red)?.replace = Some(None);
+        Ok(())
+    }
+
+    pub fn insert_before(&mut self, red: &Red, green: Vec<Green>) -> Result<(), MutationError> {
+        Self::has_parent(red)?;
+        self.edit(red)?.before.extend(green);
+        Ok(())
+    }
+
+    pub fn insert_after(&mut self, red: &Red, green: Vec<Green>) -> Result<(), MutationError> {
+        Self::has_parent(red)?;
+        self.edit(red)?.after.extend(green);
+        Ok(())
+    }
+
+    pub fn push_many(&mut self, red: &Red, green: Vec<Green>) -> Result<(), MutationError> {
+        let len = Self::children_len(red)?;
+        self.edit(red)?
+            .inserts
+            .entry(len)
+            .or_default()
+            .extend(green);
+        Ok(())
+    }
+
+    pub fn insert_many(
+        &mut self,
+        red: &Red,
+        idx: usize,
+        green: Vec<Green>,
+    ) -> Result<(), MutationError> {
+        let len = Self::children_len(red)?;
+        if idx > len {
+            return Err(MutationError::IndexOutOfBounds { index: idx, len });
+        }
+        self.edit(red)?
+            .inserts
+            .entry(idx)
+            .or_default()
+            .extend(green);
+        Ok(())
+    }
+
+    pub fn finish(self, builder: &mut dyn GreenBuilder) -> Edited {
+        let Self { root, edits } = self;
+        let mut mapping = Mapping::default();
+        let green = match &edits.replace {
+            Some(Some(green)) => {
+                mapping.replaced_root = true;
+                green.clone()
+            }
+            Some(None) => unreachable!(),
+            None => rebuild(builder, root.green(), &edits, &mut mapping),
+        };
+        Edited {
+            old: root,
+            root: Red::root(green),
+            mapping,
+        }
+    }
+
+    fn has_parent(red: &Red) -> Result<(), MutationError> {
+        match red.parent() {
+            Some(_) => Ok(()),
+            None => Err(MutationError::NoParent),
+        }
+    }
+
+    fn children_len(red: &Red) -> Result<usize, MutationError> {
+        match red.green().as_node() {
+            Some(node) => Ok(node.children.len()),
+            None if red.green().as_token().is_some() => {
+                Err(MutationError::NotANode { name: red.name() })
+            }
+            None => Err(MutationError::MissingAliasChild { name: red.name() }),
+        }
+    }
+
+    fn edit(&mut self, red: &Red) -> Result<&mut EditNode, MutationError> {
+        let path = path(red);
+        let root = red.ancestors().last().unwrap_or_else(|| red.clone());
+        if !root.green().ptr_eq(&self.root.green()) {
+            return Err(MutationError::ForeignNode);
+        }
+        Ok(path.into_iter().fold(&mut self.edits, |edit, idx| {
+            edit.children.entry(idx).or_default()
+        }))
+    }
+}
+
+fn path(red: &Red) -> Vec<usize> {
+    let mut path = red
+        .ancestors()
+        .filter_map(|red| red.kind().as_child().map(|(_, idx, _)| idx))
+        .collect::<Vec<_>>();
+    path.reverse();
+    path
+}
+
+fn rebuild(
+    builder: &mut dyn GreenBuilder,
+    green: Green,
+    edits: &EditNode,
+    mapping: &mut Mapping,
+) -> Green {
+    if edits.children.is_empty() && edits.inserts.is_empty() {
+        return green;
+    }
+
+    let old_children = green.children().collect::<Vec<_>>();
+    let mut children = Vec::with_capacity(old_children.len());
+    mapping.indices = vec![None; old_children.len()];
+
+    for (idx, child) in old_children.into_iter().enumerate() {
+        if let Some(inserted) = edits.inserts.get(&idx) {
+            children.extend(inserted.iter().cloned());
+        }
+        let edit = match edits.children.get(&idx) {
+            Some(edit) => edit,
+            None => {
+                mapping.indices[idx] = Some(children.len());
+                children.push(child);
+                continue;
+            }
+        };
+        children.extend(edit.before.iter().cloned());
+        match &edit.replace {
+            Some(None) => (),
+            Some(Some(replacement)) => {
+                mapping.indices[idx] = Some(children.len());
+                mapping.replaced.insert(idx);
+                children.push(replacement.clone());
+            }
+            None => {
+                mapping.indices[idx] = Some(children.len());
+                let child_mapping = mapping.children.entry(idx).or_default();
+                children.push(rebuild(builder, child, edit, child_mapping));
+            }
+        }
+        children.extend(edit.after.iter().cloned());
+    }
+    let len = mapping.indices.len();
+    for (_, inserted) in edits.inserts.range(len..) {
+        children.extend(inserted.iter().cloned());
+    }
+
+    builder.replace_children(green, children)
+}
+
+/// Old child index to new child index, for every rebuilt node.
+#[derive(Debug, Default)]
+struct Mapping {
+    replaced_root: bool,
+    indices: Vec<Option<usize>>,
+    replaced: BTreeSet<usize>,
+    children: BTreeMap<usize, Mapping>,
+}
+
+#[derive(Debug)]
+pub struct Edited {
+    old: Red,
+    root: Red,
+    mapping: Mapping,
+}
+
+impl Edited {
+    pub fn root(&self) -> &Red {
+        &self.root
+    }
+
+    pub fn green(&self) -> Green {
+        self.root.green()
+    }
+
+    /// Finds the node that took place of `old` in the new tree.
+    ///
+    /// Returns `None` for removed nodes and for nodes inside replaced subtrees.
+    pub fn map(&self, old: &Red) -> Option<Red> {
+        let old_root = old.ancestors().last().unwrap_or_else(|| old.clone());
+        if !old_root.green().ptr_eq(&self.old.green()) {
+            return None;
+        }
+
+        let path = path(old);
+        if self.mapping.replaced_root {
+            return if path.is_empty() {
+                Some(self.root.clone())
+            } else {
+                None
+            };
+        }
+
+        let mut mapping = Some(&self.mapping);
+        let mut red = self.root.clone();
+        let mut replaced = false;
+        for idx in path {
+            if replaced {
+                return None;
+            }
+            let new_idx = match mapping {
+                Some(m) if !m.indices.is_empty() => {
+                    replaced = m.replaced.contains(&idx);
+                    let new_idx = m.indices.get(idx).copied().flatten()?;
+                    mapping = m.children.get(&idx);
+                    new_idx
+                }
+                _ => {
+                    mapping = None;
+                    idx
+                }
+            };
+            red = red.nth_child(new_idx)?;
+        }
+        Some(red)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::tree;
+    use crate::*;
+
+    #[test]
+    fn batch() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+        let value = root.first_child().unwrap();
+        let one = value.first_child().unwrap();
+        let comma = value.next_sibling().unwrap();
+        let list = root.last_child().unwrap();
+        let five = list.last_child().unwrap();
+
+        let mut editor = Editor::new(one.clone());
+        editor.replace(&one, builder.token("number", "6")).unwrap();
+        editor.remove(&comma).unwrap();
+        editor
+            .insert_before(&list, vec![builder.token("semi", ";")])
+            .unwrap();
+        editor
+            .push_many(&list, vec![builder.token("op", "!")])
+            .unwrap();
+        let edited = editor.finish(&mut builder);
+
+        assert_eq!("6+ 2+3;45!", edited.green().to_string());
+        assert!(edited.root().first_child().unwrap().is("Value"));
+        assert!(edited.root().first_child().unwrap().is_alias());
+
+        assert_eq!(None, edited.map(&comma));
+        let new_five = edited.map(&five).unwrap();
+        assert_eq!("5", new_five.green().to_string());
+        assert_eq!(TextRange::new(8.into(), 9.into()), new_five.text_range());
+        let new_one = edited.map(&one).unwrap();
+        assert_eq!("6", new_one.green().to_string());
+        assert_eq!(None, edited.map(&Red::root(builder.token("number", "1"))));
+    }
+
+    #[test]
+    fn untouched_subtrees_are_shared() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+        let comma = root.nth_child(1).unwrap();
+
+        let mut editor = Editor::new(root.clone());
+        editor
+            .insert_after(&comma, vec![builder.token("comma", ",")])
+            .unwrap();
+        let edited = editor.finish(&mut builder);
+
+        assert_eq!("1 + 2+3,,45", edited.green().to_string());
+        let first = edited.root().first_child().unwrap();
+        assert!(first.green().ptr_eq(&root.first_child().unwrap().green()));
+        assert_eq!(
+            Some(edited.root().last_child().unwrap()),
+            edited.map(&root.last_child().unwrap())
+        );
+    }
+
+    #[test]
+    fn invalid_edits() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+        let comma = root.nth_child(1).unwrap();
+        let list = root.last_child().unwrap();
+
+        let mut editor = Editor::new(root.clone());
+        assert_eq!(Err(MutationError::NoParent), editor.remove(&root));
+        assert_eq!(
+            Err(MutationError::NoParent),
+            editor.insert_after(&root, vec![])
+        );
+        assert_eq!(
+            Err(MutationError::NotANode {
+                name: Kind::new("comma")
+            }),
+            editor.push_many(&comma, vec![])
+        );
+        assert_eq!(
+            Err(MutationError::IndexOutOfBounds { index: 4, len: 2 }),
+            editor.insert_many(&list, 4, vec![])
+        );
+        let other = Red::root(builder.token("comma", ","));
+        assert_eq!(
+            Err(MutationError::ForeignNode),
+            editor.replace(&other, builder.token("comma", ";"))
+        );
+
+        let edited = editor.finish(&mut builder);
+        assert!(edited.green().ptr_eq(&root.green()));
+    }
+}
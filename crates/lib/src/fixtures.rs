@@ -0,0 +1,36 @@
+use crate::{Cache, Green, GreenKind, Red};
+
+/// `1 + 2+3,45`: a sum aliased as `Value` with a nested sum, a comma and a list.
+pub(crate) fn tree(builder: &mut Cache) -> Red {
+    let tree = builder.node("Root", |builder| {
+        vec![
+            builder.alias("Value", |builder| {
+                builder.node("Add", |builder| {
+                    vec![
+                        builder.with_trivia("number", "", "1", " "),
+                        builder.with_trivia("op", "", "+", " "),
+                        builder.node("Add", |builder| {
+                            vec![
+                                builder.token("number", "2"),
+                                builder.token("op", "+"),
+                                builder.token("number", "3"),
+                            ]
+                        }),
+                    ]
+                })
+            }),
+            builder.token("comma", ","),
+            builder.node("List", |builder| {
+                vec![builder.token("number", "4"), builder.token("number", "5")]
+            }),
+        ]
+    });
+    Red::root(tree)
+}
+
+pub(crate) fn aliased(green: &Green) -> &Green {
+    match green.kind() {
+        GreenKind::Alias(Some(child)) => child,
+        _ => panic!("Expected alias"),
+    }
+}
@@ -1,35 +1,87 @@
-use std::fmt::{Debug, Display};
-use std::sync::Arc;
+use std::fmt::Display;
 
 use smol_str::SmolStr;
+use text_size::{TextLen, TextSize};
 
-pub type Name = &'static str;
+use crate::arena::GreenPtr;
+use crate::{Kind, Trivia};
+
+/// Former name of [`Kind`].
+pub type Name = Kind;
 
 #[derive(Debug, PartialEq)]
 pub struct GreenData {
-    pub name: Name,
-    pub size: usize,
+    pub name: Kind,
+    pub size: TextSize,
     pub kind: GreenKind,
 }
 
-#[derive(PartialEq, Clone)]
-pub struct Green(pub(crate) Arc<GreenData>);
+impl GreenData {
+    pub(crate) fn node(name: Kind, children: Vec<Green>) -> Self {
+        Self {
+            name,
+            size: children.iter().map(|g| g.text_len()).sum(),
+            kind: GreenKind::Node(Node { children }),
+        }
+    }
+
+    pub(crate) fn alias(name: Kind, child: Option<Green>) -> Self {
+        Self {
+            name,
+            size: child
+                .as_ref()
+                .map(|child| child.text_len())
+                .unwrap_or_default(),
+            kind: GreenKind::Alias(child),
+        }
+    }
+
+    pub(crate) fn token(name: Kind, leading: Trivia, value: SmolStr, trailing: Trivia) -> Self {
+        Self {
+            name,
+            size: leading.text_len() + value.as_str().text_len() + trailing.text_len(),
+            kind: GreenKind::Token(Token {
+                leading,
+                value,
+                trailing,
+            }),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Green(pub(crate) GreenPtr);
+
+impl PartialEq for Green {
+    fn eq(&self, other: &Green) -> bool {
+        self.ptr_eq(other) || *self.0 == *other.0
+    }
+}
 
 impl Green {
-    pub fn name(&self) -> Name {
+    /// Element in its own heap allocation, outside of any arena.
+    pub(crate) fn new(data: GreenData) -> Self {
+        Self(GreenPtr::new(data))
+    }
+
+    pub fn name(&self) -> Kind {
         self.0.name
     }
 
-    pub fn is(&self, name: Name) -> bool {
+    pub fn is(&self, name: impl Into<Kind>) -> bool {
+        self.is_kind(name.into())
+    }
+
+    fn is_kind(&self, name: Kind) -> bool {
         if let GreenKind::Alias(Some(green)) = &self.0.kind {
-            if green.is(name) {
+            if green.is_kind(name) {
                 return true;
             }
         }
         self.0.name == name
     }
 
-    pub fn size(&self) -> usize {
+    pub fn text_len(&self) -> TextSize {
         self.0.size
     }
 
@@ -37,6 +89,14 @@ impl Green {
         &self.0.kind
     }
 
+    pub fn ptr_eq(&self, other: &Green) -> bool {
+        self.0.ptr_eq(&other.0)
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const GreenData {
+        self.0.as_ptr()
+    }
+
     pub fn is_alias(&self) -> bool {
         matches!(&self.0.kind, GreenKind::Alias(_))
     }
@@ -72,10 +132,11 @@ pub struct Node {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
-    pub leading: SmolStr,
+    pub leading: Trivia,
     pub value: SmolStr,
-    pub trailing: SmolStr,
+    pub trailing: Trivia,
 }
 
 #[derive(Debug, PartialEq)]
@@ -85,70 +146,6 @@ pub enum GreenKind {
     Token(Token),
 }
 
-fn fmt_green(
-    green: &Green,
-    f: &mut std::fmt::Formatter<'_>,
-    mut offset: usize,
-    width: usize,
-    skip_indent: bool,
-) -> std::fmt::Result {
-    if width > 0 && !skip_indent {
-        write!(f, "{:width$}", " ", width = width)?;
-    }
-    write!(f, "{}", green.0.name)?;
-
-    if let GreenKind::Alias(_) = &green.0.kind {
-        write!(f, ", ")?;
-    } else {
-        write!(f, " @ {}..{}", offset, offset + green.size())?;
-    }
-
-    match &green.0.kind {
-        GreenKind::Node(Node { children }) => {
-            writeln!(f)?;
-            let width = width + 4;
-            for child in children {
-                fmt_green(child, f, offset, width, false)?;
-                offset += child.size();
-            }
-        }
-        GreenKind::Alias(Some(child)) => {
-            fmt_green(child, f, offset, width, true)?;
-        }
-        GreenKind::Alias(None) => {
-            write!(f, " missing")?;
-        }
-        GreenKind::Token(Token {
-            value,
-            leading,
-            trailing,
-        }) => {
-            write!(f, " `{}`", value)?;
-            if !leading.is_empty() {
-                write!(f, " ; leading: `{}`", fmt_debug_str(leading))?;
-            }
-            if !trailing.is_empty() {
-                write!(f, " ; trailing: `{}`", fmt_debug_str(trailing))?;
-            }
-            writeln!(f)?;
-        }
-    }
-    Ok(())
-}
-
-fn fmt_debug_str(s: &SmolStr) -> String {
-    s.replace("\t", "\\t").replace("\n", "\\n")
-}
-
-impl Debug for Green {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let width = f.width().unwrap_or_default();
-        writeln!(f, "\n--- GREEN TREE ---")?;
-        fmt_green(self, f, 0, width, false)?;
-        writeln!(f, "--- END ---")
-    }
-}
-
 impl Display for Green {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.0.kind {
@@ -222,6 +219,140 @@ mod tests {
         assert_eq!(3, builder.size());
     }
 
+    #[test]
+    fn builder_shares_subtrees() {
+        let mut builder = Cache::default();
+        let add = |builder: &mut Cache| {
+            builder.node("Add", |builder| {
+                vec![
+                    builder.token("number", "2"),
+                    builder.token("op", "+"),
+                    builder.token("number", "2"),
+                ]
+            })
+        };
+        let left = add(&mut builder);
+        let right = add(&mut builder);
+        let trivia = builder.with_trivia("number", " ", "2", "");
+
+        assert!(left.ptr_eq(&right));
+        assert!(!trivia.ptr_eq(&left.children().next().unwrap()));
+        assert_eq!(4, builder.size());
+    }
+
+    #[test]
+    fn builder_shares_parents_of_wide_nodes() {
+        let mut builder = Cache::default();
+        let list = |builder: &mut Cache| {
+            builder.alias("Value", |builder| {
+                builder.node("List", |builder| {
+                    (0..6)
+                        .map(|i| builder.token("number", i.to_string()))
+                        .collect()
+                })
+            })
+        };
+        let left = list(&mut builder);
+        let right = list(&mut builder);
+
+        assert!(left.ptr_eq(&right));
+        assert_eq!(7, builder.size());
+    }
+
+    #[test]
+    fn builder_stats() {
+        let mut builder = Cache::default();
+        builder.node("Root", |builder| {
+            vec![
+                builder.token("number", "2"),
+                builder.token("op", "+"),
+                builder.token("number", "2"),
+            ]
+        });
+
+        let stats = builder.stats();
+        assert_eq!(1, stats.hits);
+        assert_eq!(3, stats.misses);
+        assert_eq!(3, stats.entries);
+    }
+
+    #[test]
+    fn builder_policy() {
+        let mut builder = Cache::with_policy(CachePolicy::tokens_only());
+        let left = builder.node("Add", |builder| vec![builder.token("number", "2")]);
+        let right = builder.node("Add", |builder| vec![builder.token("number", "2")]);
+
+        assert!(!left.ptr_eq(&right));
+        assert_eq!(1, builder.size());
+
+        let mut builder = Cache::with_policy(CachePolicy::default().with_max_node_width(1));
+        let wide = |builder: &mut Cache| {
+            builder.node("Add", |builder| {
+                vec![builder.token("number", "2"), builder.token("number", "3")]
+            })
+        };
+        let left = wide(&mut builder);
+        let right = wide(&mut builder);
+
+        assert!(!left.ptr_eq(&right));
+        assert_eq!(2, builder.size());
+    }
+
+    #[test]
+    fn builder_max_entries() {
+        let mut builder = Cache::with_policy(CachePolicy::default().with_max_entries(2));
+        let two = builder.token("number", "2");
+        builder.token("number", "3");
+        builder.token("number", "4");
+
+        let stats = builder.stats();
+        assert_eq!(1, stats.evictions);
+        assert_eq!(2, stats.entries);
+        assert!(two.ptr_eq(&builder.token("number", "2")));
+    }
+
+    #[test]
+    fn builder_max_entries_in_use() {
+        let mut builder = Cache::with_policy(CachePolicy::default().with_max_entries(10));
+        let live = (0..20)
+            .map(|i| builder.token("number", i.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(10, builder.stats().entries);
+
+        drop(live);
+        let again = (20..40)
+            .map(|i| builder.token("number", i.to_string()))
+            .collect::<Vec<_>>();
+        let stats = builder.stats();
+        assert_eq!(10, stats.evictions);
+        assert_eq!(10, stats.entries);
+        assert!(again[0].ptr_eq(&builder.token("number", "20")));
+    }
+
+    #[test]
+    fn builder_collect() {
+        let mut builder = Cache::default();
+        let tree = builder.node("Root", |builder| {
+            vec![builder.node("Add", |builder| {
+                vec![
+                    builder.token("number", "2"),
+                    builder.token("op", "+"),
+                    builder.token("number", "2"),
+                ]
+            })]
+        });
+        let two = builder.token("number", "2");
+
+        assert_eq!(0, builder.collect());
+        assert_eq!(4, builder.size());
+
+        drop(tree);
+
+        assert_eq!(3, builder.collect());
+        assert_eq!(1, builder.size());
+        assert!(two.ptr_eq(&builder.token("number", "2")));
+    }
+
     #[test]
     fn print_trivia() {
         let mut builder = Cache::default();
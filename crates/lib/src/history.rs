@@ -0,0 +1,277 @@
+use std::fmt;
+
+use crate::{Green, GreenBuilder, Red};
+
+/// Undo/redo stack of tree versions.
+///
+/// Green trees are persistent, so every entry just keeps the roots alive and
+/// shares all untouched subtrees with its neighbours.
+#[derive(Debug)]
+pub struct History {
+    current: Green,
+    undo: Vec<HistoryEntry>,
+    redo: Vec<HistoryEntry>,
+    transaction: Option<HistoryEntry>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub description: String,
+    pub before: Green,
+    pub after: Green,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryError {
+    TransactionStarted,
+    NoTransaction,
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryError::TransactionStarted => write!(f, "Transaction already started"),
+            HistoryError::NoTransaction => write!(f, "No transaction started"),
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+impl History {
+    pub fn new(root: Green) -> Self {
+        Self {
+            current: root,
+            undo: vec![],
+            redo: vec![],
+            transaction: None,
+            limit: None,
+        }
+    }
+
+    /// Keeps at most `limit` undo entries, dropping the oldest ones.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self.truncate();
+        self
+    }
+
+    pub fn current(&self) -> &Green {
+        &self.current
+    }
+
+    pub fn root(&self) -> Red {
+        Red::root(self.current.clone())
+    }
+
+    pub fn undo_stack(&self) -> &[HistoryEntry] {
+        &self.undo
+    }
+
+    pub fn redo_stack(&self) -> &[HistoryEntry] {
+        &self.redo
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Records `root` as the new version of the tree, unless it is the current one.
+    pub fn record(&mut self, root: Green, description: impl Into<String>) {
+        if root.ptr_eq(&self.current) {
+            return;
+        }
+        let before = std::mem::replace(&mut self.current, root);
+        if let Some(transaction) = &mut self.transaction {
+            transaction.after = self.current.clone();
+            return;
+        }
+        self.push(HistoryEntry {
+            description: description.into(),
+            before,
+            after: self.current.clone(),
+        });
+    }
+
+    /// Runs `f` on the current root, for example a [`GreenMutate`](crate::GreenMutate)
+    /// call, and records its result.
+    pub fn edit(
+        &mut self,
+        builder: &mut dyn GreenBuilder,
+        description: impl Into<String>,
+        f: impl FnOnce(&mut dyn GreenBuilder, Red) -> Green,
+    ) -> &Green {
+        let root = f(builder, self.root());
+        self.record(root, description);
+        &self.current
+    }
+
+    /// Groups every following edit into one entry until [`History::commit`].
+    pub fn begin(&mut self, description: impl Into<String>) -> Result<(), HistoryError> {
+        if self.transaction.is_some() {
+            return Err(HistoryError::TransactionStarted);
+        }
+        self.transaction = Some(HistoryEntry {
+            description: description.into(),
+            before: self.current.clone(),
+            after: self.current.clone(),
+        });
+        Ok(())
+    }
+
+    pub fn commit(&mut self) -> Result<(), HistoryError> {
+        let transaction = self.transaction.take().ok_or(HistoryError::NoTransaction)?;
+        if !transaction.before.ptr_eq(&transaction.after) {
+            self.push(transaction);
+        }
+        Ok(())
+    }
+
+    /// Drops edits made since [`History::begin`].
+    pub fn rollback(&mut self) -> Result<(), HistoryError> {
+        let transaction = self.transaction.take().ok_or(HistoryError::NoTransaction)?;
+        self.current = transaction.before;
+        Ok(())
+    }
+
+    pub fn transaction(
+        &mut self,
+        description: impl Into<String>,
+        f: impl FnOnce(&mut Self),
+    ) -> Result<&Green, HistoryError> {
+        self.begin(description)?;
+        f(self);
+        self.commit()?;
+        Ok(&self.current)
+    }
+
+    /// Returns `None` when there is nothing to undo or a transaction is open.
+    pub fn undo(&mut self) -> Option<&Green> {
+        if self.transaction.is_some() {
+            return None;
+        }
+        let entry = self.undo.pop()?;
+        self.current = entry.before.clone();
+        self.redo.push(entry);
+        Some(&self.current)
+    }
+
+    /// Returns `None` when there is nothing to redo or a transaction is open.
+    pub fn redo(&mut self) -> Option<&Green> {
+        if self.transaction.is_some() {
+            return None;
+        }
+        let entry = self.redo.pop()?;
+        self.current = entry.after.clone();
+        self.undo.push(entry);
+        Some(&self.current)
+    }
+
+    fn push(&mut self, entry: HistoryEntry) {
+        self.redo.clear();
+        self.undo.push(entry);
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        if let Some(limit) = self.limit {
+            let excess = self.undo.len().saturating_sub(limit);
+            self.undo.drain(..excess);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::tree;
+    use crate::*;
+
+    /// Replaces a number of the list, which ends the text of the tree.
+    fn set_number(builder: &mut dyn GreenBuilder, root: Red, idx: usize, value: &str) -> Green {
+        let number = root.last_child().unwrap().nth_child(idx).unwrap();
+        let new = builder.token("number", value);
+        number.replace(builder, new)
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut builder = Cache::default();
+        let mut history = History::new(tree(&mut builder).green());
+
+        history.edit(&mut builder, "left", |b, root| set_number(b, root, 0, "3"));
+        history.edit(&mut builder, "right", |b, root| set_number(b, root, 1, "4"));
+        assert_eq!("1 + 2+3,34", history.current().to_string());
+
+        assert_eq!("1 + 2+3,35", history.undo().unwrap().to_string());
+        assert_eq!("1 + 2+3,45", history.undo().unwrap().to_string());
+        assert_eq!(None, history.undo());
+        assert_eq!("1 + 2+3,35", history.redo().unwrap().to_string());
+        assert_eq!("right", history.redo_stack()[0].description);
+
+        history.edit(&mut builder, "other", |b, root| set_number(b, root, 1, "6"));
+        assert!(!history.can_redo());
+        assert_eq!("1 + 2+3,36", history.current().to_string());
+
+        let value = history.current().children().next().unwrap();
+        let old_value = history.undo_stack()[0].before.children().next().unwrap();
+        assert!(value.ptr_eq(&old_value));
+    }
+
+    #[test]
+    fn transactions() {
+        let mut builder = Cache::default();
+        let mut history = History::new(tree(&mut builder).green());
+
+        history
+            .transaction("swap", |history| {
+                history.edit(&mut builder, "left", |b, root| set_number(b, root, 0, "5"));
+                history.edit(&mut builder, "right", |b, root| set_number(b, root, 1, "4"));
+            })
+            .unwrap();
+        assert_eq!("1 + 2+3,54", history.current().to_string());
+        assert_eq!(1, history.undo_stack().len());
+        assert_eq!("swap", history.undo_stack()[0].description);
+
+        history.begin("discarded").unwrap();
+        history.edit(&mut builder, "left", |b, root| set_number(b, root, 0, "7"));
+        assert_eq!(
+            Err(HistoryError::TransactionStarted),
+            history.begin("nested")
+        );
+        assert_eq!(None, history.undo());
+        history.rollback().unwrap();
+        assert_eq!("1 + 2+3,54", history.current().to_string());
+        assert_eq!(Err(HistoryError::NoTransaction), history.commit());
+        assert_eq!(Err(HistoryError::NoTransaction), history.rollback());
+
+        assert_eq!("1 + 2+3,45", history.undo().unwrap().to_string());
+    }
+
+    #[test]
+    fn unchanged_root_is_not_recorded() {
+        let mut builder = Cache::default();
+        let mut history = History::new(tree(&mut builder).green());
+
+        history.edit(&mut builder, "same", |b, root| set_number(b, root, 0, "4"));
+        history.record(history.current().clone(), "noop");
+
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn limit() {
+        let mut builder = Cache::default();
+        let mut history = History::new(tree(&mut builder).green()).with_limit(1);
+
+        history.edit(&mut builder, "a", |b, root| set_number(b, root, 0, "3"));
+        history.edit(&mut builder, "b", |b, root| set_number(b, root, 0, "6"));
+
+        assert_eq!("1 + 2+3,35", history.undo().unwrap().to_string());
+        assert_eq!(None, history.undo());
+    }
+}
@@ -0,0 +1,180 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::{Debug, Display};
+use std::sync::{OnceLock, RwLock};
+
+/// Interned name of a green element.
+///
+/// Kinds are compared by id. Ordering follows names, so sorting kinds gives
+/// the same result as sorting the strings they were created from.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Kind(u16);
+
+#[derive(Default)]
+struct Registry {
+    names: Vec<&'static str>,
+    ids: HashMap<&'static str, u16>,
+}
+
+fn registry() -> &'static RwLock<Registry> {
+    static REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut registry = Registry::default();
+        registry.names.push("");
+        registry.ids.insert("", 0);
+        RwLock::new(registry)
+    })
+}
+
+/// Returned by [`Kind::try_new`] once every id is taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KindError;
+
+impl Display for KindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Too many kinds registered")
+    }
+}
+
+impl std::error::Error for KindError {}
+
+impl Kind {
+    /// Panics once every id is taken, use [`Kind::try_new`] for untrusted names.
+    pub fn new(name: &str) -> Self {
+        Self::try_new(name).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(name: &str) -> Result<Self, KindError> {
+        if let Some(id) = registry().read().unwrap().ids.get(name) {
+            return Ok(Self(*id));
+        }
+
+        let mut registry = registry().write().unwrap();
+        if let Some(id) = registry.ids.get(name) {
+            return Ok(Self(*id));
+        }
+        let id = u16::try_from(registry.names.len()).map_err(|_| KindError)?;
+        // Registered names live as long as the registry, which is forever.
+        // There are at most `u16::MAX` of them.
+        let name: &'static str = Box::leak(name.into());
+        registry.names.push(name);
+        registry.ids.insert(name, id);
+        Ok(Self(id))
+    }
+
+    pub fn from_id(id: u16) -> Option<Self> {
+        let registry = registry().read().unwrap();
+        if usize::from(id) < registry.names.len() {
+            Some(Self(id))
+        } else {
+            None
+        }
+    }
+
+    pub fn id(self) -> u16 {
+        self.0
+    }
+
+    pub fn name(self) -> &'static str {
+        registry().read().unwrap().names[usize::from(self.0)]
+    }
+}
+
+/// Creates a [`Kind`] once per call site, so repeated checks are integer compares.
+///
+/// Only literals are accepted, for other names use [`Kind::new`].
+#[macro_export]
+macro_rules! kind {
+    ($name: literal) => {{
+        static KIND: ::std::sync::OnceLock<$crate::Kind> = ::std::sync::OnceLock::new();
+        *KIND.get_or_init(|| $crate::Kind::new($name))
+    }};
+}
+
+impl From<&str> for Kind {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl PartialEq<str> for Kind {
+    fn eq(&self, other: &str) -> bool {
+        self.name() == other
+    }
+}
+
+impl PartialEq<&str> for Kind {
+    fn eq(&self, other: &&str) -> bool {
+        self.name() == *other
+    }
+}
+
+impl PartialEq<Kind> for str {
+    fn eq(&self, other: &Kind) -> bool {
+        self == other.name()
+    }
+}
+
+impl PartialEq<Kind> for &str {
+    fn eq(&self, other: &Kind) -> bool {
+        *self == other.name()
+    }
+}
+
+impl PartialOrd for Kind {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Kind {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.0 == other.0 {
+            return Ordering::Equal;
+        }
+        self.name().cmp(other.name())
+    }
+}
+
+impl Debug for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.name(), f)
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self.name(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn interned() {
+        let number = Kind::new("number");
+
+        assert_eq!(number, Kind::new("number"));
+        assert_ne!(number, Kind::new("op"));
+        assert_eq!("number", number.name());
+        assert_eq!(Some(number), Kind::from_id(number.id()));
+        assert_eq!(number, kind!("number"));
+        assert_eq!("", Kind::default().name());
+        assert_eq!(Ok(number), Kind::try_new("number"));
+    }
+
+    #[test]
+    fn ordered_by_name() {
+        let value = Kind::new("Value");
+        let list = Kind::new("List");
+        let atom = Kind::new("atom");
+
+        let mut kinds = vec![atom, value, list];
+        kinds.sort();
+
+        assert_eq!(vec![list, value, atom], kinds);
+    }
+}
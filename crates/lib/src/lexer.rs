@@ -0,0 +1,100 @@
+use smol_str::SmolStr;
+
+use crate::PatternError;
+
+/// Character level scanner shared by the [`Pattern`](crate::Pattern) and
+/// [`Query`](crate::Query) parsers.
+pub(crate) struct Lexer<'a> {
+    pub text: &'a str,
+    pub pos: usize,
+    /// Starts a comment running to the end of the line.
+    comment: Option<char>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            pos: 0,
+            comment: None,
+        }
+    }
+
+    pub fn with_comments(mut self, comment: char) -> Self {
+        self.comment = Some(comment);
+        self
+    }
+
+    pub fn error(&self, desc: &str) -> PatternError {
+        PatternError::new(desc, self.pos)
+    }
+
+    pub fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    pub fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn skip_ws(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => self.pos += c.len_utf8(),
+                Some(c) if Some(c) == self.comment => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Alphanumeric characters, `_`, `-` and any of `extra`, possibly empty.
+    pub fn ident(&mut self, extra: &str) -> &'a str {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-' || extra.contains(c))
+        {
+            self.bump();
+        }
+        &self.text[start..self.pos]
+    }
+
+    /// Rest of a string literal, after the opening `"`.
+    pub fn string(&mut self) -> Result<SmolStr, PatternError> {
+        let mut value = String::new();
+        loop {
+            let c = self
+                .bump()
+                .ok_or_else(|| self.error("Unterminated string"))?;
+            match c {
+                '"' => return Ok(value.into()),
+                '\\' => {
+                    let c = self
+                        .bump()
+                        .ok_or_else(|| self.error("Unterminated string"))?;
+                    value.push(match c {
+                        'n' => '\n',
+                        't' => '\t',
+                        c => c,
+                    });
+                }
+                c => value.push(c),
+            }
+        }
+    }
+}
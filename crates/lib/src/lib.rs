@@ -1,11 +1,56 @@
 pub use ast::{AliasBuilder, Ast, AstBuilder, IntoBuilder, TokenBuilder};
-pub use builder::Cache;
+pub use builder::{Cache, CachePolicy, CacheStats, GreenBuilder, GreenBuilderExt};
+pub use cursor::RedCursor;
+pub use debug::{AliasStyle, DebugOptions, DebugTree, OffsetUnit};
+pub use diff::{diff, DiffOp};
+pub use editor::{Edited, Editor};
 pub use green::{Green, GreenData, GreenKind, Name, Node, Token};
-pub use mutation::{replace_green, GreenMutate};
-pub use red::Red;
+pub use history::{History, HistoryEntry, HistoryError};
+pub use kind::{Kind, KindError};
+pub use mutation::{replace_green, replace_green_with_edits, GreenMutate, MutationError};
+pub use ptr::{AstPtr, NodePtr};
+pub use query::{Query, QueryCapture, QueryMatch};
+pub use red::{Preorder, Red, TokenAtOffset, WalkEvent};
+pub use rewrite::{rewrite, Rewrite, Rewriter};
+pub use search::{Match, Pattern, PatternError, Template};
+pub use shared::SharedCache;
+pub use snapshot::{SnapshotError, SNAPSHOT_VERSION};
+pub use text_edit::TextEdit;
+pub use text_size::{TextLen, TextRange, TextSize};
+pub use trivia::{Trivia, TriviaKind, TriviaPiece};
 
+mod arena;
 mod ast;
 mod builder;
+mod cursor;
+mod dag;
+mod debug;
+mod diff;
+mod editor;
+#[cfg(test)]
+mod fixtures;
 mod green;
+mod history;
+// Generated code of the JSON example, for tests of casts to generated enums.
+#[cfg(test)]
+#[allow(clippy::all)]
+#[path = "../examples/json/generated/mod.rs"]
+mod json;
+mod kind;
+mod lexer;
 mod mutation;
+mod ptr;
+mod query;
 mod red;
+mod rewrite;
+mod search;
+#[cfg(feature = "serde")]
+mod serialize;
+mod shared;
+mod snapshot;
+mod text_edit;
+mod trivia;
+
+// Lets the generated code of the examples name this crate in tests.
+#[cfg(test)]
+extern crate self as microtree;
@@ -1,98 +1,304 @@
-use crate::Cache;
-use crate::Node;
+use std::fmt;
+
+use crate::rewrite::rewrite_children;
+use crate::GreenBuilder;
 use crate::Red;
-use crate::{Green, GreenKind};
+use crate::TextEdit;
+use crate::{diff, rewrite, Green, Kind, Rewrite, Rewriter, TextSize};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MutationError {
+    NotANode {
+        name: Kind,
+    },
+    IndexOutOfBounds {
+        index: usize,
+        len: usize,
+    },
+    MissingAliasChild {
+        name: Kind,
+    },
+    NoParent,
+    /// The node belongs to another tree than the one being edited.
+    ForeignNode,
+}
+
+impl fmt::Display for MutationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MutationError::NotANode { name } => write!(f, "`{}` is not a node", name),
+            MutationError::IndexOutOfBounds { index, len } => {
+                write!(f, "Index {} is out of bounds for {} children", index, len)
+            }
+            MutationError::MissingAliasChild { name } => {
+                write!(f, "Alias `{}` has no child", name)
+            }
+            MutationError::NoParent => write!(f, "Root has no parent"),
+            MutationError::ForeignNode => write!(f, "Node does not belong to the edited tree"),
+        }
+    }
+}
+
+impl std::error::Error for MutationError {}
 
+/// Mutations returning the new root.
+///
+/// Every panicking method has a `try_` variant returning [`MutationError`] instead,
+/// only the `try_` variants have to be implemented.
 pub trait GreenMutate {
-    fn replace(&self, builder: &mut Cache, green: Green) -> Green;
-    fn push_many(&self, builder: &mut Cache, green: Vec<Green>) -> Green;
-    fn insert_many(&self, builder: &mut Cache, idx: usize, green: Vec<Green>) -> Green;
-    fn remove(&self, builder: &mut Cache) -> Option<Green>;
+    fn try_replace(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        green: Green,
+    ) -> Result<Green, MutationError>;
+    fn try_push_many(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        green: Vec<Green>,
+    ) -> Result<Green, MutationError>;
+    fn try_insert_many(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        idx: usize,
+        green: Vec<Green>,
+    ) -> Result<Green, MutationError>;
+    fn try_remove(&self, builder: &mut dyn GreenBuilder) -> Result<Green, MutationError>;
+
+    fn try_replace_with_edit(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        green: Green,
+    ) -> Result<(Green, TextEdit), MutationError>;
+    fn try_push_many_with_edit(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        green: Vec<Green>,
+    ) -> Result<(Green, TextEdit), MutationError>;
+    fn try_insert_many_with_edit(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        idx: usize,
+        green: Vec<Green>,
+    ) -> Result<(Green, TextEdit), MutationError>;
+    fn try_remove_with_edit(
+        &self,
+        builder: &mut dyn GreenBuilder,
+    ) -> Result<(Green, TextEdit), MutationError>;
+
+    fn replace(&self, builder: &mut dyn GreenBuilder, green: Green) -> Green {
+        unwrap(self.try_replace(builder, green))
+    }
+
+    fn push_many(&self, builder: &mut dyn GreenBuilder, green: Vec<Green>) -> Green {
+        unwrap(self.try_push_many(builder, green))
+    }
+
+    fn insert_many(&self, builder: &mut dyn GreenBuilder, idx: usize, green: Vec<Green>) -> Green {
+        unwrap(self.try_insert_many(builder, idx, green))
+    }
+
+    fn remove(&self, builder: &mut dyn GreenBuilder) -> Option<Green> {
+        no_parent(self.try_remove(builder))
+    }
+
+    fn replace_with_edit(&self, builder: &mut dyn GreenBuilder, green: Green) -> (Green, TextEdit) {
+        unwrap(self.try_replace_with_edit(builder, green))
+    }
+
+    fn push_many_with_edit(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        green: Vec<Green>,
+    ) -> (Green, TextEdit) {
+        unwrap(self.try_push_many_with_edit(builder, green))
+    }
+
+    fn insert_many_with_edit(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        idx: usize,
+        green: Vec<Green>,
+    ) -> (Green, TextEdit) {
+        unwrap(self.try_insert_many_with_edit(builder, idx, green))
+    }
+
+    fn remove_with_edit(&self, builder: &mut dyn GreenBuilder) -> Option<(Green, TextEdit)> {
+        no_parent(self.try_remove_with_edit(builder))
+    }
+}
+
+fn text_of(green: &[Green]) -> String {
+    green.iter().map(Green::to_string).collect()
+}
+
+fn unwrap<T>(result: Result<T, MutationError>) -> T {
+    result.unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn no_parent<T>(result: Result<T, MutationError>) -> Option<T> {
+    match result {
+        Err(MutationError::NoParent) => None,
+        result => Some(unwrap(result)),
+    }
+}
+
+fn check_index(index: usize, children: &[Green]) -> Result<(), MutationError> {
+    if index < children.len() {
+        Ok(())
+    } else {
+        Err(MutationError::IndexOutOfBounds {
+            index,
+            len: children.len(),
+        })
+    }
 }
 
 impl GreenMutate for Red {
-    fn remove(&self, builder: &mut Cache) -> Option<Green> {
+    fn try_remove(&self, builder: &mut dyn GreenBuilder) -> Result<Green, MutationError> {
         match self.kind().as_child() {
             Some((parent, index, _)) => {
                 let mut children = parent.green().children().collect::<Vec<_>>();
 
+                check_index(index, &children)?;
                 children.remove(index);
 
-                let new_parent = builder.node(parent.name(), |_| children);
-                Some(parent.replace(builder, new_parent))
+                let new_parent = builder.try_replace_children(parent.green(), children)?;
+                parent.try_replace(builder, new_parent)
             }
-            None => None,
+            None => Err(MutationError::NoParent),
         }
     }
-    fn replace(&self, builder: &mut Cache, green: Green) -> Green {
+
+    fn try_replace(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        green: Green,
+    ) -> Result<Green, MutationError> {
         match self.kind().as_child() {
             Some((parent, index, _)) => {
-                let mut replacement = Some(green);
-                let children = parent
-                    .green()
-                    .children()
-                    .enumerate()
-                    .map(|(i, child)| {
-                        if i == index {
-                            replacement.take().unwrap()
-                        } else {
-                            child
-                        }
-                    })
-                    .collect();
-                let new_parent = builder.replace_children(parent.green(), children);
-                parent.replace(builder, new_parent)
+                let mut children = parent.green().children().collect::<Vec<_>>();
+
+                check_index(index, &children)?;
+                children[index] = green;
+                let new_parent = builder.try_replace_children(parent.green(), children)?;
+                parent.try_replace(builder, new_parent)
             }
-            None => green,
+            None => Ok(green),
         }
     }
 
-    fn push_many(&self, builder: &mut Cache, mut new_green: Vec<Green>) -> Green {
-        assert!(self.is_node());
-
+    fn try_push_many(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        mut new_green: Vec<Green>,
+    ) -> Result<Green, MutationError> {
         let mut children = self.green().children().collect::<Vec<_>>();
         children.append(&mut new_green);
-        let new_red = builder.node(self.name(), |_| children);
-        self.replace(builder, new_red)
+        let new_red = builder.try_replace_children(self.green(), children)?;
+        self.try_replace(builder, new_red)
     }
 
-    fn insert_many(&self, builder: &mut Cache, idx: usize, green: Vec<Green>) -> Green {
-        assert!(self.is_node());
-
+    fn try_insert_many(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        idx: usize,
+        green: Vec<Green>,
+    ) -> Result<Green, MutationError> {
         let mut children = self.green().children().collect::<Vec<_>>();
 
+        if idx > children.len() {
+            return Err(MutationError::IndexOutOfBounds {
+                index: idx,
+                len: children.len(),
+            });
+        }
         let after = children.split_off(idx);
 
-        let children = children
-            .into_iter()
-            .chain(green.into_iter())
-            .chain(after.into_iter())
-            .collect();
+        let children = children.into_iter().chain(green).chain(after).collect();
+
+        let new_red = builder.try_replace_children(self.green(), children)?;
+        self.try_replace(builder, new_red)
+    }
 
-        let new_red = builder.node(self.name(), |_| children);
-        self.replace(builder, new_red)
+    fn try_replace_with_edit(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        green: Green,
+    ) -> Result<(Green, TextEdit), MutationError> {
+        let edit = TextEdit::replace(self.text_range(), green.to_string());
+        Ok((self.try_replace(builder, green)?, edit))
+    }
+
+    fn try_push_many_with_edit(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        green: Vec<Green>,
+    ) -> Result<(Green, TextEdit), MutationError> {
+        let edit = TextEdit::insert(self.text_range().end(), text_of(&green));
+        Ok((self.try_push_many(builder, green)?, edit))
+    }
+
+    fn try_insert_many_with_edit(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        idx: usize,
+        green: Vec<Green>,
+    ) -> Result<(Green, TextEdit), MutationError> {
+        let offset = self.offset()
+            + self
+                .green()
+                .children()
+                .take(idx)
+                .map(|child| child.text_len())
+                .sum::<TextSize>();
+        let edit = TextEdit::insert(offset, text_of(&green));
+        Ok((self.try_insert_many(builder, idx, green)?, edit))
+    }
+
+    fn try_remove_with_edit(
+        &self,
+        builder: &mut dyn GreenBuilder,
+    ) -> Result<(Green, TextEdit), MutationError> {
+        let edit = TextEdit::delete(self.text_range());
+        Ok((self.try_remove(builder)?, edit))
     }
 }
 
+/// Applies `f` top-down to every green, including ones returned by `f`.
+///
+/// See [`Rewriter`] for bottom-up rewrites with parent context.
 pub fn replace_green(
-    builder: &mut Cache,
+    builder: &mut dyn GreenBuilder,
     node: Green,
-    f: impl Clone + Fn(&mut Cache, Green) -> Green,
+    f: impl Fn(&mut dyn GreenBuilder, Green) -> Green,
 ) -> Green {
-    let new = f(builder, node);
-    if let GreenKind::Node(Node { children }) = &new.kind() {
-        let new_children = children
-            .iter()
-            .map(|child| replace_green(builder, child.clone(), f.clone()))
-            .collect::<Vec<_>>();
-
-        let node = builder.node(new.name(), move |_| new_children);
-        node
-    } else {
-        new
+    rewrite(builder, &Red::root(node), &mut TopDown(f))
+}
+
+struct TopDown<F>(F);
+
+impl<F: Fn(&mut dyn GreenBuilder, Green) -> Green> Rewriter for TopDown<F> {
+    fn enter(&mut self, builder: &mut dyn GreenBuilder, red: &Red) -> Rewrite {
+        let new = (self.0)(builder, red.green());
+        if new.ptr_eq(red.green_ref()) {
+            Rewrite::Continue
+        } else {
+            Rewrite::Replace(rewrite_children(builder, &Red::root(new), self))
+        }
     }
 }
 
+/// Like [`replace_green`], but also returns minimal text edits turning the old text into the new one.
+pub fn replace_green_with_edits(
+    builder: &mut dyn GreenBuilder,
+    node: Green,
+    f: impl Fn(&mut dyn GreenBuilder, Green) -> Green,
+) -> (Green, Vec<TextEdit>) {
+    let new = replace_green(builder, node.clone(), f);
+    let edits = diff(&node, &new).iter().map(|op| op.text_edit()).collect();
+    (new, edits)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -278,6 +484,234 @@ mod tests {
         assert_eq!("2+2", result);
     }
 
+    #[test]
+    fn replace_with_edit() {
+        let mut builder = Cache::default();
+        let tree = builder.node("Root", |builder| {
+            vec![builder.node("Add", |builder| {
+                vec![
+                    builder.with_trivia("number", "", "2", " "),
+                    builder.with_trivia("op", "", "+", " "),
+                    builder.token("number", "2"),
+                ]
+            })]
+        });
+
+        let root = Root::new(Red::root(tree.clone())).unwrap();
+        let second = root.op().unwrap().right().unwrap();
+        let new_token = builder.token("number", "30");
+        let (new_tree, edit) = second.0.replace_with_edit(&mut builder, new_token);
+
+        assert_eq!(
+            TextEdit::replace(TextRange::new(4.into(), 5.into()), "30"),
+            edit
+        );
+
+        let mut text = tree.to_string();
+        edit.apply(&mut text);
+        assert_eq!(new_tree.to_string(), text);
+    }
+
+    #[test]
+    fn insert_and_remove_with_edit() {
+        let mut builder = Cache::default();
+        let tree = builder.node("Root", |builder| {
+            vec![
+                builder.node("Add", |builder| {
+                    vec![
+                        builder.token("number", "1"),
+                        builder.token("op", "+"),
+                        builder.token("number", "2"),
+                    ]
+                }),
+                builder.token("comma", ","),
+            ]
+        });
+
+        let root = Root::new(Red::root(tree.clone())).unwrap();
+        let new_op = vec![builder.token("number", "3"), builder.token("comma", ",")];
+        let (_, edit) = root.0.insert_many_with_edit(&mut builder, 1, new_op);
+        assert_eq!(TextEdit::insert(3.into(), "3,"), edit);
+
+        let four = vec![builder.token("number", "4")];
+        let (_, edit) = root.0.push_many_with_edit(&mut builder, four);
+        assert_eq!(TextEdit::insert(4.into(), "4"), edit);
+
+        let comma = root.commas().next().unwrap();
+        let (new_tree, edit) = comma.0.remove_with_edit(&mut builder).unwrap();
+        assert_eq!(TextEdit::delete(TextRange::new(3.into(), 4.into())), edit);
+        assert_eq!("1+2", new_tree.to_string());
+        assert_eq!(None, root.0.remove_with_edit(&mut builder));
+    }
+
+    #[test]
+    fn remove_ws_with_edits() {
+        let mut builder = Cache::default();
+        let tree = builder.node("Root", |builder| {
+            vec![builder.node("Add", |builder| {
+                vec![
+                    builder.with_trivia("number", "", "2", " "),
+                    builder.with_trivia("op", "", "+", " "),
+                    builder.token("number", "2"),
+                ]
+            })]
+        });
+
+        let (new_tree, edits) =
+            replace_green_with_edits(&mut builder, tree.clone(), |builder, node| {
+                match &node.kind() {
+                    GreenKind::Token(Token { value, .. }) => {
+                        builder.token(node.name(), value.clone())
+                    }
+                    _ => node,
+                }
+            });
+
+        assert_eq!(
+            vec![
+                TextEdit::replace(TextRange::new(0.into(), 2.into()), "2"),
+                TextEdit::replace(TextRange::new(2.into(), 4.into()), "+"),
+            ],
+            edits
+        );
+
+        let mut text = tree.to_string();
+        TextEdit::apply_all(&edits, &mut text);
+        assert_eq!(new_tree.to_string(), text);
+    }
+
+    fn aliased_list(builder: &mut Cache) -> Green {
+        builder.node("Root", |builder| {
+            vec![builder.alias("Value", |builder| {
+                builder.node("List", |builder| {
+                    vec![
+                        builder.token("number", "1"),
+                        builder.token("comma", ","),
+                        builder.token("number", "2"),
+                    ]
+                })
+            })]
+        })
+    }
+
+    #[test]
+    fn mutations_keep_aliases() {
+        use crate::json::*;
+
+        let mut builder = Cache::default();
+        let array = Array::build()
+            .fill(
+                LBracket::build(),
+                vec![Number::build(1).into_dyn(), Number::build(2).into_dyn()],
+                Comma::build(),
+                RBracket::build(),
+            )
+            .into_builder()
+            .build_green(&mut builder);
+        let root = Red::root(builder.node("Root", |_| vec![array]));
+        let value = root.first_child().unwrap();
+        assert_eq!("[1,2,]", root.green().to_string());
+        assert!(value.is_alias());
+        assert!(Value::new(value.clone())
+            .and_then(Value::as_array)
+            .is_some());
+
+        let bracket = value.last_child().unwrap();
+        let tree = bracket.remove(&mut builder).unwrap();
+        let value = Red::root(tree.clone()).first_child().unwrap();
+        assert_eq!("[1,2,", tree.to_string());
+        assert!(Value::new(value.clone())
+            .and_then(Value::as_array)
+            .is_some());
+
+        let rest = vec![
+            Number::build(3).build_green(&mut builder),
+            RBracket::build().build_green(&mut builder),
+        ];
+        let tree = value.push_many(&mut builder, rest);
+        let value = Red::root(tree.clone()).first_child().unwrap();
+        assert_eq!("[1,2,3]", tree.to_string());
+        assert!(Value::new(value.clone())
+            .and_then(Value::as_array)
+            .is_some());
+
+        let zero = vec![
+            Number::build(0).build_green(&mut builder),
+            Comma::build().build_green(&mut builder),
+        ];
+        let tree = value.insert_many(&mut builder, 1, zero);
+        let value = Red::root(tree.clone()).first_child().unwrap();
+        assert_eq!("[0,1,2,3]", tree.to_string());
+        let array = Value::new(value).and_then(Value::as_array).unwrap();
+        assert_eq!(4, array.values().count());
+    }
+
+    #[test]
+    fn replace_green_keeps_aliases() {
+        let mut builder = Cache::default();
+        let tree = aliased_list(&mut builder);
+
+        let tree = replace_green(&mut builder, tree, |builder, node| {
+            if node.is("number") {
+                builder.token("number", "9")
+            } else {
+                node
+            }
+        });
+
+        let value = Red::root(tree.clone()).first_child().unwrap();
+        assert_eq!("9,9", tree.to_string());
+        assert!(value.is_alias() && value.is("List"));
+    }
+
+    #[test]
+    fn fallible_mutations() {
+        let mut builder = Cache::default();
+        let root = Red::root(aliased_list(&mut builder));
+        let value = root.first_child().unwrap();
+        let one = value.first_child().unwrap();
+        let extra = vec![builder.token("number", "3")];
+
+        assert_eq!(
+            Err(MutationError::NotANode {
+                name: Kind::new("number")
+            }),
+            one.try_push_many(&mut builder, extra.clone())
+        );
+        assert_eq!(
+            Err(MutationError::IndexOutOfBounds { index: 4, len: 3 }),
+            value.try_insert_many(&mut builder, 4, extra.clone())
+        );
+        assert_eq!(Err(MutationError::NoParent), root.try_remove(&mut builder));
+        assert_eq!(
+            "1,23",
+            value
+                .try_insert_many(&mut builder, 3, extra)
+                .unwrap()
+                .to_string()
+        );
+
+        let empty = builder.alias("Value", |_| None);
+        let children = vec![builder.token("number", "1")];
+        assert_eq!(
+            Err(MutationError::MissingAliasChild {
+                name: Kind::new("Value")
+            }),
+            builder.try_replace_children(empty, children)
+        );
+
+        let bogus = Red::child(builder.token("number", "3"), value, 5, 0.into());
+        let two = builder.token("number", "2");
+        assert_eq!(
+            Err(MutationError::IndexOutOfBounds { index: 5, len: 3 }),
+            bogus.try_replace(&mut builder, two)
+        );
+        assert_eq!(
+            Err(MutationError::IndexOutOfBounds { index: 5, len: 3 }),
+            bogus.try_remove(&mut builder)
+        );
+    }
+
     #[derive(Debug)]
     struct Root(Red);
     impl Root {
@@ -0,0 +1,200 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use text_size::TextRange;
+
+use crate::{Ast, Kind, Red};
+
+/// Cheap handle to a [`Red`] element, which does not keep its tree alive.
+///
+/// The element is identified by its name and text range, so the pointer
+/// resolves in a newer root as long as the edits left that range untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodePtr {
+    name: Kind,
+    range: TextRange,
+}
+
+impl NodePtr {
+    pub fn new(red: &Red) -> Self {
+        Self {
+            name: red.name(),
+            range: red.text_range(),
+        }
+    }
+
+    pub fn name(&self) -> Kind {
+        self.name
+    }
+
+    pub fn text_range(&self) -> TextRange {
+        self.range
+    }
+
+    /// Finds the element in `root`, or `None` if it no longer exists.
+    pub fn to_red(&self, root: &Red) -> Option<Red> {
+        if !root.text_range().contains_range(self.range) {
+            return None;
+        }
+        self.find(root.clone())
+    }
+
+    fn find(&self, element: Red) -> Option<Red> {
+        if element.text_range() == self.range && element.name() == self.name {
+            return Some(element);
+        }
+        element
+            .children_touching(self.range)
+            .filter(|child| child.text_range().contains_range(self.range))
+            .find_map(|child| self.find(child))
+    }
+}
+
+/// Typed [`NodePtr`].
+pub struct AstPtr<N: Ast> {
+    ptr: NodePtr,
+    _phantom: PhantomData<fn() -> N>,
+}
+
+impl<N: Ast> AstPtr<N> {
+    pub fn new(node: &N) -> Self {
+        Self {
+            ptr: NodePtr::new(&node.red()),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn node_ptr(&self) -> NodePtr {
+        self.ptr
+    }
+
+    pub fn text_range(&self) -> TextRange {
+        self.ptr.range
+    }
+
+    /// Finds the node in `root`, or `None` if it no longer exists or has a different shape.
+    pub fn to_ast(&self, root: &Red) -> Option<N> {
+        N::new(self.ptr.to_red(root)?)
+    }
+}
+
+impl<N: Ast> Clone for AstPtr<N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<N: Ast> Copy for AstPtr<N> {}
+
+impl<N: Ast> PartialEq for AstPtr<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+
+impl<N: Ast> Eq for AstPtr<N> {}
+
+impl<N: Ast> Hash for AstPtr<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ptr.hash(state)
+    }
+}
+
+impl<N: Ast> fmt::Debug for AstPtr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AstPtr").field(&self.ptr).finish()
+    }
+}
+
+impl<N: Ast> From<AstPtr<N>> for NodePtr {
+    fn from(ptr: AstPtr<N>) -> Self {
+        ptr.ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    struct Add(Red);
+
+    impl Ast for Add {
+        fn new(node: Red) -> Option<Self> {
+            if !node.is("Add") {
+                return None;
+            }
+            Some(Self(node))
+        }
+
+        fn red(&self) -> Red {
+            self.0.clone()
+        }
+    }
+
+    fn add(builder: &mut Cache, left: &str) -> Green {
+        builder.alias("Value", |builder| {
+            builder.node("Add", |builder| {
+                vec![
+                    builder.token("number", left.to_string()),
+                    builder.token("op", "+"),
+                    builder.token("number", "2"),
+                ]
+            })
+        })
+    }
+
+    #[test]
+    fn resolves_after_edits() {
+        let mut builder = Cache::default();
+        let tree = builder.node("Root", |builder| {
+            vec![
+                add(builder, "1"),
+                builder.token("comma", ","),
+                add(builder, "3"),
+            ]
+        });
+        let root = Red::root(tree);
+        let first = root.first_child().unwrap();
+        let ptr = AstPtr::new(&Add::new(first.clone()).unwrap());
+        let op = NodePtr::new(&first.children().nth(1).unwrap());
+        assert_eq!(Kind::new("Value"), ptr.node_ptr().name());
+        assert_eq!(TextRange::new(0.into(), 3.into()), ptr.text_range());
+
+        let new_root = Red::root(root.last_child().unwrap().remove(&mut builder).unwrap());
+        drop(root);
+        let found = ptr.to_ast(&new_root).unwrap();
+        assert_eq!("1+2", found.red().green().to_string());
+        assert!(op.to_red(&new_root).unwrap().is("op"));
+
+        let replacement = add(&mut builder, "11");
+        let new_root = Red::root(
+            new_root
+                .first_child()
+                .unwrap()
+                .replace(&mut builder, replacement),
+        );
+        assert!(ptr.to_ast(&new_root).is_none());
+        assert!(op.to_red(&new_root).is_none());
+    }
+
+    #[test]
+    fn same_range_different_names() {
+        let mut builder = Cache::default();
+        let tree = builder.node("Root", |builder| vec![add(builder, "1")]);
+        let root = Red::root(tree);
+
+        let ptr = NodePtr::new(&root);
+        assert!(ptr.to_red(&root).unwrap().is("Root"));
+
+        let value = root.first_child().unwrap();
+        let found = NodePtr::new(&value).to_red(&root).unwrap();
+        assert!(found.is("Value") && found.parent().is_some());
+
+        let number = NodePtr::new(&value.first_child().unwrap());
+        assert!(number.to_red(&root).unwrap().is("number"));
+        let other = Red::root(builder.token("number", "1"));
+        assert!(number.to_red(&other).is_some());
+        assert!(NodePtr::new(&value).to_red(&other).is_none());
+    }
+}
@@ -0,0 +1,790 @@
+#[cfg(feature = "regex")]
+use regex_lite::Regex;
+use smol_str::SmolStr;
+use std::collections::{HashMap, HashSet};
+
+use crate::lexer::Lexer;
+use crate::{Kind, PatternError, Red, TextRange, WalkEvent};
+
+/// Tree-sitter like query.
+///
+/// Syntax:
+/// * `(Name child...)` - element named `Name`, children match in order, other children
+///   may appear in between,
+/// * `(_ child...)` - any node, `_` - any element, `"text"` - token with the given value,
+/// * `[a b]` - alternation, `(a b)` - group of consecutive siblings,
+/// * `?`, `*`, `+` after a pattern - quantifiers, `@name` - capture,
+/// * `(#eq? @a @b)`, `(#eq? @a "text")`, `(#match? @a "regex")` and their `#not-` forms
+///   - predicates over captured text, without trivia. `#match?` needs the `regex` feature.
+///
+/// Every element of the tree is tried against every top-level pattern, each distinct
+/// set of captures satisfying the predicates is reported.
+#[derive(Debug, Clone)]
+pub struct Query {
+    patterns: Vec<QueryPattern>,
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryMatch {
+    pub pattern: usize,
+    pub captures: Vec<QueryCapture>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryCapture {
+    pub name: SmolStr,
+    pub red: Red,
+}
+
+impl QueryMatch {
+    pub fn get(&self, name: &str) -> Option<&Red> {
+        self.captures
+            .iter()
+            .find(|capture| capture.name == name)
+            .map(|capture| &capture.red)
+    }
+
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Red> + 'a {
+        self.captures
+            .iter()
+            .filter(move |capture| capture.name == name)
+            .map(|capture| &capture.red)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct QueryPattern {
+    item: Item,
+    predicates: Vec<Predicate>,
+}
+
+#[derive(Debug, Clone)]
+struct Item {
+    pat: Pat,
+    quantifier: Quantifier,
+    captures: Vec<SmolStr>,
+}
+
+#[derive(Debug, Clone)]
+enum Pat {
+    /// `None` name matches anything, `node` requires a node.
+    Element {
+        name: Option<Kind>,
+        node: bool,
+        children: Vec<Item>,
+    },
+    Token(SmolStr),
+    Alternation(Vec<Item>),
+    Group(Vec<Item>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Quantifier {
+    One,
+    Optional,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+#[derive(Debug, Clone)]
+enum Predicate {
+    Eq(SmolStr, Arg, bool),
+    #[cfg(feature = "regex")]
+    Match(SmolStr, Regex, bool),
+}
+
+#[derive(Debug, Clone)]
+enum Arg {
+    Capture(SmolStr),
+    Text(SmolStr),
+}
+
+type Captures = Vec<QueryCapture>;
+type Next<'a> = &'a mut dyn FnMut(Siblings, &mut Search) -> bool;
+
+/// Steps a search from one element may take, matches past it are not reported.
+const STEP_LIMIT: usize = 1 << 20;
+
+/// State of a search from one element.
+struct Search {
+    captures: Captures,
+    steps: usize,
+}
+
+impl Search {
+    /// Counts a step, `true` once the limit is exceeded and the search has to stop.
+    fn exhausted(&mut self) -> bool {
+        self.steps += 1;
+        self.steps > STEP_LIMIT
+    }
+}
+
+/// Key of captures which can be equal, so found matches are compared in buckets.
+fn captures_key(captures: &Captures) -> Vec<(SmolStr, TextRange, Kind)> {
+    captures
+        .iter()
+        .map(|capture| {
+            let red = &capture.red;
+            (
+                capture.name.clone(),
+                red.text_range(),
+                red.green_ref().name(),
+            )
+        })
+        .collect()
+}
+
+/// Remaining siblings, walked lazily from `first`.
+#[derive(Clone)]
+struct Siblings {
+    first: Option<Red>,
+    /// Number of siblings consumed before `first`.
+    index: usize,
+}
+
+impl Siblings {
+    fn from(red: Red) -> Self {
+        Self {
+            first: Some(red),
+            index: 0,
+        }
+    }
+
+    fn children(red: &Red) -> Self {
+        Self {
+            first: red.first_child(),
+            index: 0,
+        }
+    }
+
+    fn split_first(&self) -> Option<(Red, Siblings)> {
+        let red = self.first.clone()?;
+        let rest = Siblings {
+            first: red.next_sibling(),
+            index: self.index + 1,
+        };
+        Some((red, rest))
+    }
+}
+
+impl Item {
+    fn capture_names(&self, names: &mut Vec<SmolStr>) {
+        names.extend(self.captures.iter().cloned());
+        match &self.pat {
+            Pat::Element { children, .. } | Pat::Alternation(children) | Pat::Group(children) => {
+                children.iter().for_each(|child| child.capture_names(names))
+            }
+            Pat::Token(_) => {}
+        }
+    }
+}
+
+impl Query {
+    pub fn new(source: &str) -> Result<Self, PatternError> {
+        let mut parser = QueryParser {
+            lexer: Lexer::new(source).with_comments(';'),
+            references: vec![],
+        };
+        let mut patterns = vec![];
+        loop {
+            parser.lexer.skip_ws();
+            if parser.lexer.peek().is_none() {
+                break;
+            }
+            let mut predicates = vec![];
+            let item = match parser.item(&mut predicates)? {
+                Some(item) => item,
+                None => return Err(parser.lexer.error("Predicate outside of a pattern")),
+            };
+            let mut names = vec![];
+            item.capture_names(&mut names);
+            if let Some((_, offset)) = parser
+                .references
+                .drain(..)
+                .find(|(name, _)| !names.contains(name))
+            {
+                return Err(PatternError::new("Unknown capture", offset));
+            }
+            let item = match item {
+                Item {
+                    pat: Pat::Group(mut items),
+                    quantifier: Quantifier::One,
+                    captures,
+                } if items.len() == 1 && captures.is_empty() => items.remove(0),
+                item => item,
+            };
+            patterns.push(QueryPattern { item, predicates });
+        }
+        Ok(Self { patterns })
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        self.patterns.len()
+    }
+
+    /// Matches in preorder of their first element, then in pattern order.
+    ///
+    /// The search from each element is bounded, patterns with very many ways to
+    /// match may report only some of them.
+    pub fn matches(&self, root: &Red) -> Vec<QueryMatch> {
+        let mut matches = vec![];
+        for event in root.preorder_with_tokens() {
+            let red = match event {
+                WalkEvent::Enter(red) => red,
+                WalkEvent::Leave(_) => continue,
+            };
+            for (idx, pattern) in self.patterns.iter().enumerate() {
+                let mut found: Vec<Captures> = vec![];
+                let mut buckets: HashMap<_, Vec<usize>> = HashMap::new();
+                let mut search = Search {
+                    captures: vec![],
+                    steps: 0,
+                };
+                match_item(
+                    &pattern.item,
+                    Siblings::from(red.clone()),
+                    &mut search,
+                    &mut |rest, search| {
+                        if rest.index == 0 || !check(&pattern.predicates, &search.captures) {
+                            // Keep backtracking to find every match.
+                            return false;
+                        }
+                        let bucket = buckets.entry(captures_key(&search.captures)).or_default();
+                        if !bucket.iter().any(|&i| found[i] == search.captures) {
+                            bucket.push(found.len());
+                            found.push(search.captures.clone());
+                        }
+                        false
+                    },
+                );
+                matches.extend(found.into_iter().map(|captures| QueryMatch {
+                    pattern: idx,
+                    captures,
+                }));
+            }
+        }
+        matches
+    }
+}
+
+fn match_seq(items: &[Item], reds: Siblings, gaps: bool, search: &mut Search, next: Next) -> bool {
+    let (item, rest) = match items.split_first() {
+        Some(split) => split,
+        None => return next(reds, search),
+    };
+    let mut start = reds;
+    loop {
+        if match_item(item, start.clone(), search, &mut |remaining, search| {
+            match_seq(rest, remaining, gaps, search, next)
+        }) {
+            return true;
+        }
+        match start.split_first() {
+            Some((_, tail)) if gaps => start = tail,
+            _ => return false,
+        }
+        if search.exhausted() {
+            return true;
+        }
+    }
+}
+
+fn match_item(item: &Item, reds: Siblings, search: &mut Search, next: Next) -> bool {
+    let mut capture = |remaining: Siblings, search: &mut Search| {
+        let len = search.captures.len();
+        let mut current = reds.clone();
+        while current.index < remaining.index {
+            let (red, tail) = match current.split_first() {
+                Some(split) => split,
+                None => break,
+            };
+            for name in &item.captures {
+                search.captures.push(QueryCapture {
+                    name: name.clone(),
+                    red: red.clone(),
+                });
+            }
+            current = tail;
+            if search.exhausted() {
+                return true;
+            }
+        }
+        if next(remaining, search) {
+            return true;
+        }
+        search.captures.truncate(len);
+        false
+    };
+    match item.quantifier {
+        Quantifier::One => match_pat(&item.pat, reds.clone(), search, &mut capture),
+        Quantifier::Optional => {
+            match_pat(&item.pat, reds.clone(), search, &mut capture)
+                || capture(reds.clone(), search)
+        }
+        Quantifier::ZeroOrMore => match_repeated(&item.pat, reds.clone(), 0, search, &mut capture),
+        Quantifier::OneOrMore => match_repeated(&item.pat, reds.clone(), 1, search, &mut capture),
+    }
+}
+
+/// End of a run of repetitions.
+struct End {
+    rest: Siblings,
+    /// Repetitions in the run, up to the required minimum.
+    count: usize,
+    /// Closest previous end which added captures.
+    previous: Option<usize>,
+    /// Captures added by the last repetition.
+    captures: Captures,
+}
+
+/// Matches at least `min` repetitions of `pat`, trying longer runs first.
+///
+/// Runs are extended one repetition at a time in a loop, so long runs of siblings
+/// do not grow the stack.
+fn match_repeated(pat: &Pat, reds: Siblings, min: usize, search: &mut Search, next: Next) -> bool {
+    let mut ends = vec![End {
+        rest: reds,
+        count: 0,
+        previous: None,
+        captures: vec![],
+    }];
+    // Ends without captures of their own are the same if they share the position
+    // and the previous end with captures.
+    let mut seen = HashSet::new();
+    let mut idx = 0;
+    while idx < ends.len() {
+        let from = ends[idx].rest.clone();
+        let count = (ends[idx].count + 1).min(min);
+        let previous = match ends[idx].captures.is_empty() {
+            true => ends[idx].previous,
+            false => Some(idx),
+        };
+        let mut repetitions = vec![];
+        let mut inner = Search {
+            captures: vec![],
+            steps: search.steps,
+        };
+        let exhausted = match_pat(pat, from.clone(), &mut inner, &mut |rest, inner| {
+            if rest.index > from.index {
+                repetitions.push((rest, inner.captures.clone()));
+            }
+            false
+        });
+        search.steps = inner.steps;
+        if exhausted {
+            return true;
+        }
+        for (rest, captures) in repetitions {
+            if captures.is_empty() && !seen.insert((rest.index, previous)) {
+                continue;
+            }
+            ends.push(End {
+                rest,
+                count,
+                previous,
+                captures,
+            });
+        }
+        idx += 1;
+    }
+
+    let len = search.captures.len();
+    for (idx, end) in ends.iter().enumerate().rev() {
+        if end.count < min {
+            continue;
+        }
+        let mut runs = vec![];
+        let mut current = match end.captures.is_empty() {
+            true => end.previous,
+            false => Some(idx),
+        };
+        while let Some(idx) = current {
+            runs.push(&ends[idx].captures);
+            current = ends[idx].previous;
+        }
+        for captures in runs.into_iter().rev() {
+            search.captures.extend(captures.iter().cloned());
+        }
+        if next(end.rest.clone(), search) {
+            return true;
+        }
+        search.captures.truncate(len);
+    }
+    false
+}
+
+fn match_pat(pat: &Pat, reds: Siblings, search: &mut Search, next: Next) -> bool {
+    if search.exhausted() {
+        return true;
+    }
+    match pat {
+        Pat::Element {
+            name,
+            node,
+            children,
+        } => {
+            let (red, rest) = match reds.split_first() {
+                Some(split) => split,
+                None => return false,
+            };
+            if name.is_some_and(|name| !red.is(name)) || (*node && !red.is_node()) {
+                return false;
+            }
+            if children.is_empty() {
+                return next(rest, search);
+            }
+            match_seq(
+                children,
+                Siblings::children(&red),
+                true,
+                search,
+                &mut |_, search| next(rest.clone(), search),
+            )
+        }
+        Pat::Token(text) => match reds.split_first() {
+            Some((red, rest)) if red.green_ref().as_token().is_some_and(|t| t.value == *text) => {
+                next(rest, search)
+            }
+            _ => false,
+        },
+        Pat::Alternation(items) => items
+            .iter()
+            .any(|item| match_item(item, reds.clone(), search, next)),
+        Pat::Group(items) => match_seq(items, reds, false, search, next),
+    }
+}
+
+fn text(red: &Red) -> String {
+    let range = red.trimmed_range() - red.offset();
+    red.green_ref().to_string()[range].to_string()
+}
+
+fn captured(captures: &Captures, name: &str) -> Option<String> {
+    captures
+        .iter()
+        .find(|capture| capture.name == name)
+        .map(|capture| text(&capture.red))
+}
+
+fn check(predicates: &[Predicate], captures: &Captures) -> bool {
+    predicates.iter().all(|predicate| match predicate {
+        Predicate::Eq(name, arg, negated) => {
+            let other = match arg {
+                Arg::Capture(other) => captured(captures, other),
+                Arg::Text(text) => Some(text.to_string()),
+            };
+            match (captured(captures, name), other) {
+                (Some(left), Some(right)) => (left == right) != *negated,
+                _ => false,
+            }
+        }
+        #[cfg(feature = "regex")]
+        Predicate::Match(name, regex, negated) => {
+            captured(captures, name).is_some_and(|text| regex.is_match(&text) != *negated)
+        }
+    })
+}
+
+struct QueryParser<'a> {
+    lexer: Lexer<'a>,
+    /// Captures used by predicates of the current pattern, with their offsets.
+    references: Vec<(SmolStr, usize)>,
+}
+
+impl<'a> QueryParser<'a> {
+    fn expect(&mut self, c: char, desc: &str) -> Result<(), PatternError> {
+        self.lexer.skip_ws();
+        if self.lexer.eat(c) {
+            Ok(())
+        } else {
+            Err(self.lexer.error(desc))
+        }
+    }
+
+    fn ident(&mut self) -> Result<&'a str, PatternError> {
+        match self.lexer.ident("?!") {
+            "" => Err(self.lexer.error("Expected identifier")),
+            ident => Ok(ident),
+        }
+    }
+
+    fn capture(&mut self) -> Result<SmolStr, PatternError> {
+        self.lexer.skip_ws();
+        if !self.lexer.eat('@') {
+            return Err(self.lexer.error("Expected capture"));
+        }
+        Ok(self.ident()?.into())
+    }
+
+    /// Parses items until `close`, moving nested predicates to `predicates`.
+    fn items(
+        &mut self,
+        close: char,
+        predicates: &mut Vec<Predicate>,
+    ) -> Result<Vec<Item>, PatternError> {
+        let mut items = vec![];
+        loop {
+            self.lexer.skip_ws();
+            if self.lexer.eat(close) {
+                return Ok(items);
+            }
+            if self.lexer.peek().is_none() {
+                return Err(self.lexer.error(&format!("Expected `{}`", close)));
+            }
+            items.extend(self.item(predicates)?);
+        }
+    }
+
+    /// Returns `None` for predicates.
+    fn item(&mut self, predicates: &mut Vec<Predicate>) -> Result<Option<Item>, PatternError> {
+        self.lexer.skip_ws();
+        let pat = if self.lexer.eat('"') {
+            Pat::Token(self.lexer.string()?)
+        } else if self.lexer.eat('[') {
+            let items = self.items(']', predicates)?;
+            if items.is_empty() {
+                return Err(self.lexer.error("Empty alternation"));
+            }
+            Pat::Alternation(items)
+        } else if self.lexer.eat('(') {
+            self.lexer.skip_ws();
+            match self.lexer.peek() {
+                Some('#') => {
+                    self.lexer.eat('#');
+                    predicates.push(self.predicate()?);
+                    return Ok(None);
+                }
+                Some('(') | Some('[') | Some('"') => Pat::Group(self.items(')', predicates)?),
+                _ => {
+                    let start = self.lexer.pos;
+                    let name = match self.ident()? {
+                        "_" => None,
+                        name => Some(Kind::try_new(name).map_err(|e| PatternError::new(e, start))?),
+                    };
+                    let children = self.items(')', predicates)?;
+                    Pat::Element {
+                        node: name.is_none(),
+                        name,
+                        children,
+                    }
+                }
+            }
+        } else if self.lexer.eat('_') {
+            Pat::Element {
+                name: None,
+                node: false,
+                children: vec![],
+            }
+        } else {
+            return Err(self.lexer.error("Expected pattern"));
+        };
+
+        let quantifier = if self.lexer.eat('?') {
+            Quantifier::Optional
+        } else if self.lexer.eat('*') {
+            Quantifier::ZeroOrMore
+        } else if self.lexer.eat('+') {
+            Quantifier::OneOrMore
+        } else {
+            Quantifier::One
+        };
+
+        let mut captures = vec![];
+        loop {
+            self.lexer.skip_ws();
+            if self.lexer.peek() != Some('@') {
+                break;
+            }
+            captures.push(self.capture()?);
+        }
+
+        Ok(Some(Item {
+            pat,
+            quantifier,
+            captures,
+        }))
+    }
+
+    /// Capture used by a predicate, checked once the whole pattern is parsed.
+    fn reference(&mut self) -> Result<SmolStr, PatternError> {
+        self.lexer.skip_ws();
+        let offset = self.lexer.pos;
+        let name = self.capture()?;
+        self.references.push((name.clone(), offset));
+        Ok(name)
+    }
+
+    fn predicate(&mut self) -> Result<Predicate, PatternError> {
+        let start = self.lexer.pos;
+        let name = self.ident()?;
+        let capture = self.reference()?;
+        self.lexer.skip_ws();
+        let predicate = match name {
+            "eq?" | "not-eq?" => {
+                let arg = if self.lexer.eat('"') {
+                    Arg::Text(self.lexer.string()?)
+                } else {
+                    Arg::Capture(self.reference()?)
+                };
+                Predicate::Eq(capture, arg, name == "not-eq?")
+            }
+            #[cfg(feature = "regex")]
+            "match?" | "not-match?" => {
+                let regex_start = self.lexer.pos;
+                if !self.lexer.eat('"') {
+                    return Err(self.lexer.error("Expected regex"));
+                }
+                let regex = Regex::new(&self.lexer.string()?)
+                    .map_err(|e| PatternError::new(e, regex_start))?;
+                Predicate::Match(capture, regex, name == "not-match?")
+            }
+            #[cfg(not(feature = "regex"))]
+            "match?" | "not-match?" => {
+                return Err(PatternError::new(
+                    "Regex predicates need the `regex` feature",
+                    start,
+                ))
+            }
+            _ => return Err(PatternError::new("Unknown predicate", start)),
+        };
+        self.expect(')', "Expected `)`")?;
+        Ok(predicate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn sexp(builder: &mut Cache) -> Red {
+        let tree = builder.node("List", |builder| {
+            vec![
+                builder.token("open", "("),
+                builder.with_trivia("atom", "", "define", " "),
+                builder.with_trivia("atom", "", "x", " "),
+                builder.node("List", |builder| {
+                    vec![
+                        builder.token("open", "("),
+                        builder.with_trivia("atom", "", "car", " "),
+                        builder.token("atom", "xs"),
+                        builder.token("close", ")"),
+                    ]
+                }),
+                builder.token("close", ")"),
+            ]
+        });
+        Red::root(tree)
+    }
+
+    fn captures(m: &QueryMatch, name: &str) -> Vec<String> {
+        m.get_all(name)
+            .map(|red| red.green().to_string().trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn captures_and_quantifiers() {
+        let mut builder = Cache::default();
+        let root = sexp(&mut builder);
+
+        let query = Query::new("(List (atom) @first) (List \"(\" (atom)+ @atoms)").unwrap();
+        assert_eq!(2, query.pattern_count());
+        let matches = query.matches(&root);
+        assert_eq!(10, matches.len());
+        assert_eq!(vec!["define"], captures(&matches[0], "first"));
+        assert_eq!(vec!["x"], captures(&matches[1], "first"));
+        assert_eq!(vec!["define", "x"], captures(&matches[2], "atoms"));
+        assert_eq!(vec!["define"], captures(&matches[3], "atoms"));
+        assert_eq!(vec!["x"], captures(&matches[4], "atoms"));
+        assert_eq!(1, matches[7].pattern);
+        assert_eq!(vec!["car", "xs"], captures(&matches[7], "atoms"));
+
+        let query = Query::new("(List [(List) (close)] @end)").unwrap();
+        let matches = query.matches(&root);
+        assert_eq!(
+            "(car xs)",
+            matches[0].get("end").unwrap().green().to_string()
+        );
+        assert_eq!(")", matches[1].get("end").unwrap().green().to_string());
+
+        let query = Query::new("(_ (atom) @a . ) ((atom) @x (atom)? @y)").unwrap_err();
+        assert_eq!("Expected pattern", query.desc());
+    }
+
+    #[test]
+    fn groups() {
+        let mut builder = Cache::default();
+        let root = sexp(&mut builder);
+
+        let query = Query::new("((atom) @a (List) @l)").unwrap();
+        let matches = query.matches(&root);
+        assert_eq!(1, matches.len());
+        assert_eq!(vec!["x"], captures(&matches[0], "a"));
+    }
+
+    #[test]
+    fn long_lists() {
+        let mut builder = Cache::default();
+        let root = Red::root(builder.node("List", |builder| {
+            (0..100_000)
+                .map(|i| builder.token("atom", i.to_string()))
+                .collect()
+        }));
+
+        let query = Query::new("(List (atom)+ @a \"99999\")").unwrap();
+        let matches = query.matches(&root);
+        assert_eq!(99_999, matches[0].get_all("a").count());
+
+        let query = Query::new("(List (atom) @a \"99999\")").unwrap();
+        let matches = query.matches(&root);
+        assert_eq!(vec!["0"], captures(&matches[0], "a"));
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn predicates() {
+        let mut builder = Cache::default();
+        let root = sexp(&mut builder);
+
+        let query = Query::new(
+            r#"
+            ; Call with a known head
+            ((List (atom) @head) (#eq? @head "car"))
+            ((atom) @short (#match? @short "^.{1,2}$") (#not-eq? @short "x"))
+            "#,
+        )
+        .unwrap();
+        let matches = query.matches(&root);
+        assert_eq!(2, matches.len());
+        assert_eq!(0, matches[0].pattern);
+        assert_eq!(
+            TextRange::new(11.into(), 14.into()),
+            matches[0].get("head").unwrap().trimmed_range()
+        );
+        assert_eq!(vec!["xs"], captures(&matches[1], "short"));
+
+        let query = Query::new(r#"(List (atom) @a (atom) @b (#eq? @a @b))"#).unwrap();
+        assert!(query.matches(&root).is_empty());
+
+        let error = Query::new(r#"((atom) @a (#match? @a "("))"#).unwrap_err();
+        assert_eq!(23, error.offset());
+        let error = Query::new("((atom) @a (#same? @a @a))").unwrap_err();
+        assert_eq!("Unknown predicate", error.desc());
+    }
+
+    #[test]
+    fn unknown_captures() {
+        let error = Query::new(r#"((atom) @a (#eq? @b "x"))"#).unwrap_err();
+        assert_eq!(("Unknown capture", 17), (error.desc(), error.offset()));
+        let error = Query::new(r#"((atom) @a (#not-eq? @a @c))"#).unwrap_err();
+        assert_eq!(("Unknown capture", 24), (error.desc(), error.offset()));
+        assert!(Query::new(r#"((#eq? @a "x") (atom) @a)"#).is_ok());
+    }
+
+    #[test]
+    #[cfg(not(feature = "regex"))]
+    fn regex_feature() {
+        let error = Query::new(r#"((atom) @a (#match? @a "x"))"#).unwrap_err();
+        assert_eq!("Regex predicates need the `regex` feature", error.desc());
+    }
+}
@@ -1,7 +1,8 @@
 use std::sync::Arc;
 
 use crate::Green;
-use crate::Name;
+use crate::Kind;
+use text_size::{TextRange, TextSize};
 
 #[derive(Debug, Clone)]
 pub struct RedData {
@@ -15,12 +16,12 @@ pub enum RedKind {
     Child {
         parent: Red,
         index: usize,
-        offset: usize,
+        offset: TextSize,
     },
 }
 
 impl RedKind {
-    pub fn as_child(&self) -> Option<(&Red, usize, usize)> {
+    pub fn as_child(&self) -> Option<(&Red, usize, TextSize)> {
         match self {
             Self::Child {
                 parent,
@@ -32,7 +33,7 @@ impl RedKind {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Red(Arc<RedData>);
 
 impl Red {
@@ -43,7 +44,7 @@ impl Red {
         }))
     }
 
-    pub fn child(green: Green, parent: Red, index: usize, offset: usize) -> Self {
+    pub fn child(green: Green, parent: Red, index: usize, offset: TextSize) -> Self {
         Self(Arc::new(RedData {
             kind: RedKind::Child {
                 parent,
@@ -58,6 +59,10 @@ impl Red {
         self.0.green.clone()
     }
 
+    pub fn green_ref(&self) -> &Green {
+        &self.0.green
+    }
+
     pub fn is_alias(&self) -> bool {
         self.0.green.is_alias()
     }
@@ -66,16 +71,21 @@ impl Red {
         self.0.green.as_node().is_some()
     }
 
+    /// Missing aliases are neither nodes nor tokens.
+    pub fn is_token(&self) -> bool {
+        self.0.green.as_token().is_some()
+    }
+
     pub fn kind(&self) -> &RedKind {
         &self.0.kind
     }
 
-    pub fn name(&self) -> Name {
-        self.green().name()
+    pub fn name(&self) -> Kind {
+        self.0.green.name()
     }
 
-    pub fn is(&self, name: Name) -> bool {
-        self.green().is(name)
+    pub fn is(&self, name: impl Into<Kind>) -> bool {
+        self.0.green.is(name)
     }
 
     pub fn parent(&self) -> Option<Red> {
@@ -83,7 +93,7 @@ impl Red {
         Some(parent.clone())
     }
 
-    pub fn offset(&self) -> usize {
+    pub fn offset(&self) -> TextSize {
         self.0
             .kind
             .as_child()
@@ -91,6 +101,28 @@ impl Red {
             .unwrap_or_default()
     }
 
+    pub fn text_range(&self) -> TextRange {
+        TextRange::at(self.offset(), self.0.green.text_len())
+    }
+
+    /// Like [`Red::text_range`], but without the leading trivia of the first token
+    /// and the trailing trivia of the last token.
+    pub fn trimmed_range(&self) -> TextRange {
+        let range = self.text_range();
+        let leading = self
+            .first_token()
+            .and_then(|token| Some(token.0.green.as_token()?.leading.text_len()))
+            .unwrap_or_default();
+        let trailing = self
+            .last_token()
+            .and_then(|token| Some(token.0.green.as_token()?.trailing.text_len()))
+            .unwrap_or_default();
+
+        let start = range.start() + leading;
+        let end = std::cmp::max(start, range.end() - trailing);
+        TextRange::new(start, end)
+    }
+
     pub fn children(&self) -> impl Iterator<Item = Red> + '_ {
         let parent = self.clone();
         let mut offset = self.offset();
@@ -100,14 +132,453 @@ impl Red {
             .enumerate()
             .map(move |(idx, green_child)| {
                 let child_offset = offset;
-                offset += green_child.size();
+                offset += green_child.text_len();
                 Self::child(green_child, parent.clone(), idx, child_offset)
             })
     }
 }
 
+impl Red {
+    pub fn ancestors(&self) -> impl Iterator<Item = Red> {
+        std::iter::successors(Some(self.clone()), Red::parent)
+    }
+
+    pub fn first_child(&self) -> Option<Red> {
+        let green = self.0.green.as_node()?.children.first()?.clone();
+        Some(Self::child(green, self.clone(), 0, self.offset()))
+    }
+
+    pub fn last_child(&self) -> Option<Red> {
+        let children = &self.0.green.as_node()?.children;
+        let green = children.last()?.clone();
+        let offset = self.text_range().end() - green.text_len();
+        Some(Self::child(green, self.clone(), children.len() - 1, offset))
+    }
+
+    pub fn nth_child(&self, n: usize) -> Option<Red> {
+        let children = &self.0.green.as_node()?.children;
+        let green = children.get(n)?.clone();
+        let offset = self.offset() + children[..n].iter().map(Green::text_len).sum::<TextSize>();
+        Some(Self::child(green, self.clone(), n, offset))
+    }
+
+    pub fn next_sibling(&self) -> Option<Red> {
+        let (parent, index, offset) = self.0.kind.as_child()?;
+        let green = parent.0.green.as_node()?.children.get(index + 1)?.clone();
+        let offset = offset + self.0.green.text_len();
+        Some(Self::child(green, parent.clone(), index + 1, offset))
+    }
+
+    pub fn prev_sibling(&self) -> Option<Red> {
+        let (parent, index, offset) = self.0.kind.as_child()?;
+        let index = index.checked_sub(1)?;
+        let green = parent.0.green.as_node()?.children.get(index)?.clone();
+        let offset = offset - green.text_len();
+        Some(Self::child(green, parent.clone(), index, offset))
+    }
+
+    pub fn first_token(&self) -> Option<Red> {
+        self.descendants_with_tokens().find(Red::is_token)
+    }
+
+    pub fn last_token(&self) -> Option<Red> {
+        let mut red = self.clone();
+        loop {
+            if red.is_token() {
+                return Some(red);
+            }
+            red = std::iter::successors(red.last_child(), Red::prev_sibling)
+                .find(|child| child.first_token().is_some())?;
+        }
+    }
+
+    pub fn descendants(&self) -> impl Iterator<Item = Red> {
+        self.preorder().filter_map(|event| match event {
+            WalkEvent::Enter(red) => Some(red),
+            WalkEvent::Leave(_) => None,
+        })
+    }
+
+    pub fn descendants_with_tokens(&self) -> impl Iterator<Item = Red> {
+        self.preorder_with_tokens().filter_map(|event| match event {
+            WalkEvent::Enter(red) => Some(red),
+            WalkEvent::Leave(_) => None,
+        })
+    }
+
+    pub fn preorder(&self) -> Preorder {
+        Preorder::new(self.clone(), false)
+    }
+
+    pub fn preorder_with_tokens(&self) -> Preorder {
+        Preorder::new(self.clone(), true)
+    }
+
+    fn same(&self, other: &Red) -> bool {
+        self.offset() == other.offset() && self.0.green.ptr_eq(&other.0.green)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WalkEvent<T> {
+    Enter(T),
+    Leave(T),
+}
+
+pub struct Preorder {
+    start: Red,
+    next: Option<WalkEvent<Red>>,
+    with_tokens: bool,
+}
+
+impl Preorder {
+    fn new(start: Red, with_tokens: bool) -> Self {
+        let next = if with_tokens || !start.is_token() {
+            Some(WalkEvent::Enter(start.clone()))
+        } else {
+            None
+        };
+        Self {
+            start,
+            next,
+            with_tokens,
+        }
+    }
+
+    pub fn skip_subtree(&mut self) {
+        self.next = self.next.take().and_then(|next| match next {
+            WalkEvent::Enter(first_child) => first_child.parent().map(WalkEvent::Leave),
+            WalkEvent::Leave(parent) => Some(WalkEvent::Leave(parent)),
+        });
+    }
+
+    fn accepts(&self, red: &Red) -> bool {
+        self.with_tokens || !red.is_token()
+    }
+
+    fn first_child(&self, red: &Red) -> Option<Red> {
+        std::iter::successors(red.first_child(), Red::next_sibling).find(|c| self.accepts(c))
+    }
+
+    fn next_sibling(&self, red: &Red) -> Option<Red> {
+        std::iter::successors(red.next_sibling(), Red::next_sibling).find(|c| self.accepts(c))
+    }
+}
+
+impl Iterator for Preorder {
+    type Item = WalkEvent<Red>;
+
+    fn next(&mut self) -> Option<WalkEvent<Red>> {
+        let next = self.next.take()?;
+        self.next = match &next {
+            WalkEvent::Enter(red) => Some(match self.first_child(red) {
+                Some(child) => WalkEvent::Enter(child),
+                None => WalkEvent::Leave(red.clone()),
+            }),
+            WalkEvent::Leave(red) if red.same(&self.start) => None,
+            WalkEvent::Leave(red) => match self.next_sibling(red) {
+                Some(sibling) => Some(WalkEvent::Enter(sibling)),
+                None => red.parent().map(WalkEvent::Leave),
+            },
+        };
+        Some(next)
+    }
+}
+
+impl Red {
+    pub fn token_at_offset(&self, offset: TextSize) -> TokenAtOffset {
+        if !self.text_range().contains_inclusive(offset) {
+            return TokenAtOffset::None;
+        }
+        if self.is_token() {
+            return TokenAtOffset::Single(self.clone());
+        }
+
+        let mut tokens = self
+            .children_touching(TextRange::empty(offset))
+            .flat_map(|child| child.token_at_offset(offset));
+
+        match (tokens.next(), tokens.last()) {
+            (None, _) => TokenAtOffset::None,
+            (Some(token), None) => TokenAtOffset::Single(token),
+            (Some(left), Some(right)) => TokenAtOffset::Between(left, right),
+        }
+    }
+
+    pub fn covering_element(&self, range: TextRange) -> Option<Red> {
+        if !self.text_range().contains_range(range) {
+            return None;
+        }
+
+        let mut element = self.clone();
+        loop {
+            let child = element
+                .children_touching(range)
+                .find(|child| child.text_range().contains_range(range));
+            match child {
+                Some(child) => element = child,
+                None => break Some(element),
+            }
+        }
+    }
+
+    /// Children whose range touches `range`, skipping the rest without creating them.
+    pub(crate) fn children_touching(&self, range: TextRange) -> impl Iterator<Item = Red> + '_ {
+        let parent = self.clone();
+        self.0
+            .green
+            .children()
+            .enumerate()
+            .scan(self.offset(), |offset, (idx, green_child)| {
+                let child_offset = *offset;
+                *offset += green_child.text_len();
+                Some((idx, child_offset, green_child))
+            })
+            .skip_while(move |(_, offset, green_child)| {
+                *offset + green_child.text_len() < range.start()
+            })
+            .take_while(move |(_, offset, _)| *offset <= range.end())
+            .map(move |(idx, offset, green_child)| {
+                Self::child(green_child, parent.clone(), idx, offset)
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenAtOffset {
+    None,
+    Single(Red),
+    Between(Red, Red),
+}
+
+impl TokenAtOffset {
+    pub fn left_biased(self) -> Option<Red> {
+        match self {
+            Self::None => None,
+            Self::Single(token) | Self::Between(token, _) => Some(token),
+        }
+    }
+
+    pub fn right_biased(self) -> Option<Red> {
+        match self {
+            Self::None => None,
+            Self::Single(token) | Self::Between(_, token) => Some(token),
+        }
+    }
+}
+
+impl Iterator for TokenAtOffset {
+    type Item = Red;
+
+    fn next(&mut self) -> Option<Red> {
+        match std::mem::replace(self, Self::None) {
+            Self::None => None,
+            Self::Single(token) => Some(token),
+            Self::Between(left, right) => {
+                *self = Self::Single(right);
+                Some(left)
+            }
+        }
+    }
+}
+
 impl PartialEq for Red {
     fn eq(&self, other: &Self) -> bool {
         self.offset() == other.offset() && self.0.green == other.0.green
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::tree;
+    use crate::*;
+
+    #[test]
+    fn text_range() {
+        let mut builder = Cache::default();
+        let tree = builder.node("Root", |builder| {
+            vec![
+                builder.with_trivia("number", "", "2", " "),
+                builder.with_trivia("op", "", "+", " "),
+                builder.token("number", "2"),
+            ]
+        });
+
+        assert_eq!(TextSize::from(5), tree.text_len());
+
+        let root = Red::root(tree);
+        let ranges = root.children().map(|c| c.text_range()).collect::<Vec<_>>();
+
+        assert_eq!(TextRange::up_to(5.into()), root.text_range());
+        assert_eq!(
+            vec![
+                TextRange::new(0.into(), 2.into()),
+                TextRange::new(2.into(), 4.into()),
+                TextRange::new(4.into(), 5.into()),
+            ],
+            ranges
+        );
+    }
+
+    #[test]
+    fn token_at_offset() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+
+        let token = root.token_at_offset(1.into());
+        assert_eq!(
+            vec!["number 0..2"],
+            token
+                .map(|t| format!("{} {:?}", t.name(), t.text_range()))
+                .collect::<Vec<_>>()
+        );
+
+        let token = root.token_at_offset(7.into());
+        assert_eq!(
+            vec!["number 6..7", "comma 7..8"],
+            token
+                .map(|t| format!("{} {:?}", t.name(), t.text_range()))
+                .collect::<Vec<_>>()
+        );
+
+        let token = root.token_at_offset(10.into()).right_biased().unwrap();
+        assert_eq!("number", token.name());
+        assert_eq!(TextRange::new(9.into(), 10.into()), token.text_range());
+
+        assert_eq!(TokenAtOffset::None, root.token_at_offset(11.into()));
+    }
+
+    #[test]
+    fn covering_element() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+
+        let value = root
+            .covering_element(TextRange::new(1.into(), 3.into()))
+            .unwrap();
+        assert!(value.is("Value") && value.is("Add"));
+        assert_eq!(TextRange::new(0.into(), 7.into()), value.text_range());
+
+        let op = root
+            .covering_element(TextRange::new(2.into(), 3.into()))
+            .unwrap();
+        assert_eq!("op", op.name());
+
+        let root_again = root
+            .covering_element(TextRange::new(6.into(), 8.into()))
+            .unwrap();
+        assert_eq!("Root", root_again.name());
+
+        assert_eq!(
+            None,
+            root.covering_element(TextRange::new(9.into(), 12.into()))
+        );
+    }
+
+    #[test]
+    fn siblings() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+
+        let value = root.first_child().unwrap();
+        let comma = value.next_sibling().unwrap();
+        let list = root.last_child().unwrap();
+
+        assert_eq!("Value", value.name());
+        assert_eq!("comma", comma.name());
+        assert_eq!(TextRange::new(8.into(), 10.into()), list.text_range());
+        assert_eq!(Some(comma), list.prev_sibling());
+        assert_eq!(None, value.prev_sibling());
+        assert_eq!(None, list.next_sibling());
+
+        let plus = value.first_child().unwrap().next_sibling().unwrap();
+        let ancestors = plus.ancestors().map(|a| a.name()).collect::<Vec<_>>();
+        assert_eq!(vec!["op", "Value", "Root"], ancestors);
+
+        assert_eq!(
+            TextRange::new(0.into(), 2.into()),
+            root.first_token().unwrap().text_range()
+        );
+        assert_eq!(list.last_child(), root.last_token());
+    }
+
+    #[test]
+    fn preorder() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+
+        let nodes = root.descendants().map(|d| d.name()).collect::<Vec<_>>();
+        assert_eq!(vec!["Root", "Value", "Add", "List"], nodes);
+
+        let all = root
+            .descendants_with_tokens()
+            .map(|d| d.name())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "Root", "Value", "number", "op", "Add", "number", "op", "number", "comma", "List",
+                "number", "number"
+            ],
+            all
+        );
+
+        let events = root
+            .preorder()
+            .map(|event| match event {
+                WalkEvent::Enter(red) => format!("> {}", red.name()),
+                WalkEvent::Leave(red) => format!("< {}", red.name()),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec!["> Root", "> Value", "> Add", "< Add", "< Value", "> List", "< List", "< Root"],
+            events
+        );
+
+        let mut preorder = root.preorder_with_tokens();
+        let mut entered = vec![];
+        while let Some(event) = preorder.next() {
+            if let WalkEvent::Enter(red) = event {
+                if red.is("Add") {
+                    preorder.skip_subtree();
+                }
+                entered.push(red.name());
+            }
+        }
+        assert_eq!(
+            vec!["Root", "Value", "comma", "List", "number", "number"],
+            entered
+        );
+    }
+
+    #[test]
+    fn trimmed_range() {
+        let mut builder = Cache::default();
+        let tree = builder.node("Root", |builder| {
+            vec![builder.node("Add", |builder| {
+                vec![
+                    builder.with_trivia("number", "  ", "2", " "),
+                    builder.with_trivia("op", "", "+", " "),
+                    builder.with_trivia("number", "", "2", "\n"),
+                ]
+            })]
+        });
+        let root = Red::root(tree);
+        let add = root.first_child().unwrap();
+        let op = add.first_child().unwrap().next_sibling().unwrap();
+
+        assert_eq!(TextRange::new(0.into(), 8.into()), add.text_range());
+        assert_eq!(TextRange::new(2.into(), 7.into()), add.trimmed_range());
+        assert_eq!(TextRange::new(4.into(), 6.into()), op.text_range());
+        assert_eq!(TextRange::new(4.into(), 5.into()), op.trimmed_range());
+
+        let tree = builder.node("Root", |builder| {
+            vec![
+                builder.alias("Value", |_| None),
+                builder.with_trivia("number", "  ", "2", ""),
+            ]
+        });
+        let root = Red::root(tree);
+        assert!(root.first_token().unwrap().is("number"));
+        assert_eq!(TextRange::new(2.into(), 3.into()), root.trimmed_range());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{Green, GreenBuilder, Red};
+
+/// What [`rewrite`] should do with an entered element.
+#[derive(Debug, Clone)]
+pub enum Rewrite {
+    /// Rewrites children, then calls [`Rewriter::leave`].
+    Continue,
+    /// Keeps the element untouched without visiting its children.
+    Skip,
+    /// Uses the given green instead, without visiting children or calling `leave`.
+    Replace(Green),
+}
+
+/// Visitor driven tree rewrite.
+///
+/// Both hooks see the element in the old tree, so parents and siblings are
+/// available as context.
+pub trait Rewriter {
+    /// Called top-down, before children are rewritten.
+    fn enter(&mut self, _builder: &mut dyn GreenBuilder, _red: &Red) -> Rewrite {
+        Rewrite::Continue
+    }
+
+    /// Called bottom-up with `green` already rebuilt from rewritten children.
+    /// When no child changed, `green` is the old green itself.
+    fn leave(&mut self, _builder: &mut dyn GreenBuilder, _red: &Red, green: Green) -> Green {
+        green
+    }
+}
+
+/// Rewrites the subtree of `red`, returning its new green.
+///
+/// Nodes with no changed child are reused by pointer, aliases are kept.
+pub fn rewrite(builder: &mut dyn GreenBuilder, red: &Red, rewriter: &mut impl Rewriter) -> Green {
+    match rewriter.enter(builder, red) {
+        Rewrite::Skip => return red.green(),
+        Rewrite::Replace(green) => return green,
+        Rewrite::Continue => (),
+    }
+
+    let green = rewrite_children(builder, red, rewriter);
+    rewriter.leave(builder, red, green)
+}
+
+pub(crate) fn rewrite_children(
+    builder: &mut dyn GreenBuilder,
+    red: &Red,
+    rewriter: &mut impl Rewriter,
+) -> Green {
+    let mut changed = false;
+    let children = red
+        .children()
+        .map(|child| {
+            let new = rewrite(builder, &child, rewriter);
+            changed |= !new.ptr_eq(child.green_ref());
+            new
+        })
+        .collect::<Vec<_>>();
+
+    if changed {
+        builder.replace_children(red.green(), children)
+    } else {
+        red.green()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::tree;
+    use crate::*;
+
+    struct Fold;
+
+    impl Rewriter for Fold {
+        fn enter(&mut self, _builder: &mut dyn GreenBuilder, red: &Red) -> Rewrite {
+            if red.is("List") {
+                Rewrite::Skip
+            } else {
+                Rewrite::Continue
+            }
+        }
+
+        fn leave(&mut self, builder: &mut dyn GreenBuilder, red: &Red, green: Green) -> Green {
+            if !red.is("Add") {
+                return green;
+            }
+            let sum = green
+                .children()
+                .filter(|child| child.is("number"))
+                .map(|child| child.as_token().unwrap().value.parse::<u32>().unwrap())
+                .sum::<u32>();
+            builder.token("number", sum.to_string())
+        }
+    }
+
+    #[test]
+    fn fold_bottom_up() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+
+        let new = rewrite(&mut builder, &root, &mut Fold);
+
+        assert_eq!("6,45", new.to_string());
+        let list = new.children().nth(2).unwrap();
+        assert!(list.ptr_eq(root.last_child().unwrap().green_ref()));
+    }
+
+    struct InsideAdd;
+
+    impl Rewriter for InsideAdd {
+        fn enter(&mut self, builder: &mut dyn GreenBuilder, red: &Red) -> Rewrite {
+            let in_add = red.parent().is_some_and(|parent| parent.is("Add"));
+            if red.is("number") && in_add {
+                Rewrite::Replace(builder.token("number", "0"))
+            } else {
+                Rewrite::Continue
+            }
+        }
+    }
+
+    #[test]
+    fn context_and_sharing() {
+        let mut builder = Cache::default();
+        let root = tree(&mut builder);
+
+        let new = rewrite(&mut builder, &root, &mut InsideAdd);
+        assert_eq!("0+ 0+0,45", new.to_string());
+        assert!(Red::root(new.clone()).first_child().unwrap().is_alias());
+        let list = new.children().nth(2).unwrap();
+        assert!(list.ptr_eq(root.last_child().unwrap().green_ref()));
+
+        let same = rewrite(&mut builder, &Red::root(new.clone()), &mut InsideAdd);
+        assert!(same.ptr_eq(&new));
+    }
+}
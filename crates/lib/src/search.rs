@@ -0,0 +1,483 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use smol_str::SmolStr;
+
+use crate::lexer::Lexer;
+use crate::{rewrite, Green, GreenBuilder, Kind, Red, Rewrite, Rewriter};
+
+/// Structural pattern over red trees.
+///
+/// Syntax:
+/// * `Name(p1 p2 ...)` - node named `Name` whose children match `p1 p2 ...`,
+/// * `name` - any element named `name`,
+/// * `"text"` - token with the given value, `name:"text"` also checks its name,
+/// * `_` - any single element,
+/// * `$x` - any single element bound as `x`, `$x*` - zero or more elements.
+///
+/// Trivia is ignored, so `Cons("(" "car" "." $x ")")` matches `( car . (1 2) )`.
+/// Using the same placeholder twice requires both elements to be equal.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    root: Pat,
+}
+
+/// Replacement built from the placeholders of a [`Match`].
+///
+/// Uses the [`Pattern`] syntax, except that every token needs a name and `_`
+/// or bare names are not allowed. Placeholders are copied as they are,
+/// together with their trivia: `$x` needs exactly one bound element, `$x*`
+/// splices all of them and cannot be the root.
+#[derive(Debug, Clone)]
+pub struct Template {
+    root: Pat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    desc: String,
+    offset: usize,
+}
+
+impl PatternError {
+    pub(crate) fn new(desc: impl ToString, offset: usize) -> Self {
+        Self {
+            desc: desc.to_string(),
+            offset,
+        }
+    }
+
+    pub fn desc(&self) -> &str {
+        &self.desc
+    }
+
+    /// Byte offset in the pattern text.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.desc, self.offset)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    red: Red,
+    bindings: BTreeMap<SmolStr, Vec<Red>>,
+}
+
+impl Match {
+    pub fn red(&self) -> &Red {
+        &self.red
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Red> {
+        self.bindings.get(name)?.first()
+    }
+
+    pub fn get_many(&self, name: &str) -> &[Red] {
+        self.bindings
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Pat {
+    Any,
+    Named(Kind),
+    Token(Option<Kind>, SmolStr),
+    Node(Kind, Vec<Pat>),
+    Placeholder(SmolStr, bool),
+}
+
+impl Pattern {
+    pub fn parse(text: &str) -> Result<Self, PatternError> {
+        Ok(Self {
+            root: PatParser::new(text).parse()?,
+        })
+    }
+
+    pub fn matches(&self, red: &Red) -> Option<Match> {
+        let mut bindings = Bindings::default();
+        if match_pat(&self.root, red, &mut bindings) {
+            Some(Match {
+                red: red.clone(),
+                bindings: bindings.map,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Every match in the subtree of `red`, in preorder, including nested ones.
+    pub fn find_all(&self, red: &Red) -> Vec<Match> {
+        red.preorder_with_tokens()
+            .filter_map(|event| match event {
+                crate::WalkEvent::Enter(red) => self.matches(&red),
+                crate::WalkEvent::Leave(_) => None,
+            })
+            .collect()
+    }
+
+    /// Replaces outermost matches in the subtree of `red` with `template`.
+    pub fn replace_all(
+        &self,
+        builder: &mut dyn GreenBuilder,
+        red: &Red,
+        template: &Template,
+    ) -> Green {
+        rewrite(
+            builder,
+            red,
+            &mut Replace {
+                pattern: self,
+                template,
+            },
+        )
+    }
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self, PatternError> {
+        Ok(Self {
+            root: PatParser::new(text).template().parse()?,
+        })
+    }
+
+    /// Builds the replacement, or `None` if it uses a placeholder missing in `m`
+    /// or `$x` is bound to other than one element.
+    pub fn instantiate(&self, builder: &mut dyn GreenBuilder, m: &Match) -> Option<Green> {
+        let mut green = instantiate(&self.root, builder, m)?;
+        match green.len() {
+            1 => green.pop(),
+            _ => None,
+        }
+    }
+}
+
+struct Replace<'a> {
+    pattern: &'a Pattern,
+    template: &'a Template,
+}
+
+impl Rewriter for Replace<'_> {
+    fn enter(&mut self, builder: &mut dyn GreenBuilder, red: &Red) -> Rewrite {
+        match self.pattern.matches(red) {
+            Some(m) => match self.template.instantiate(builder, &m) {
+                Some(green) => Rewrite::Replace(green),
+                None => Rewrite::Skip,
+            },
+            None => Rewrite::Continue,
+        }
+    }
+}
+
+/// Bindings with a trail of inserted names, so failed branches can be undone
+/// without copying the map.
+#[derive(Default)]
+struct Bindings {
+    map: BTreeMap<SmolStr, Vec<Red>>,
+    trail: Vec<SmolStr>,
+}
+
+impl Bindings {
+    fn bind(&mut self, name: &SmolStr, reds: &[Red]) -> bool {
+        match self.map.get(name) {
+            Some(bound) => {
+                bound.len() == reds.len()
+                    && bound
+                        .iter()
+                        .zip(reds)
+                        .all(|(bound, red)| bound.green_ref() == red.green_ref())
+            }
+            None => {
+                self.map.insert(name.clone(), reds.to_vec());
+                self.trail.push(name.clone());
+                true
+            }
+        }
+    }
+
+    fn attempt(&mut self, f: impl FnOnce(&mut Self) -> bool) -> bool {
+        let mark = self.trail.len();
+        let found = f(self);
+        if !found {
+            for name in self.trail.drain(mark..) {
+                self.map.remove(&name);
+            }
+        }
+        found
+    }
+}
+
+fn match_pat(pat: &Pat, red: &Red, bindings: &mut Bindings) -> bool {
+    match pat {
+        Pat::Any => true,
+        Pat::Named(name) => red.is(*name),
+        Pat::Token(name, text) => {
+            name.is_none_or(|name| red.is(name))
+                && red
+                    .green_ref()
+                    .as_token()
+                    .is_some_and(|token| token.value == *text)
+        }
+        Pat::Node(name, children) => {
+            let reds = red.children().collect::<Vec<_>>();
+            red.is(*name) && red.is_node() && match_seq(children, &reds, bindings)
+        }
+        Pat::Placeholder(name, _) => bindings.bind(name, std::slice::from_ref(red)),
+    }
+}
+
+fn match_seq(pats: &[Pat], reds: &[Red], bindings: &mut Bindings) -> bool {
+    match pats.split_first() {
+        None => reds.is_empty(),
+        Some((Pat::Placeholder(name, true), rest)) => (0..=reds.len()).any(|len| {
+            bindings.attempt(|bindings| {
+                bindings.bind(name, &reds[..len]) && match_seq(rest, &reds[len..], bindings)
+            })
+        }),
+        Some((pat, rest)) => match reds.split_first() {
+            Some((red, reds)) => bindings.attempt(|bindings| {
+                match_pat(pat, red, bindings) && match_seq(rest, reds, bindings)
+            }),
+            None => false,
+        },
+    }
+}
+
+fn instantiate(pat: &Pat, builder: &mut dyn GreenBuilder, m: &Match) -> Option<Vec<Green>> {
+    Some(match pat {
+        Pat::Token(Some(name), text) => vec![builder.token(*name, text.clone())],
+        Pat::Node(name, children) => {
+            let mut green = vec![];
+            for child in children {
+                green.extend(instantiate(child, builder, m)?);
+            }
+            vec![builder.node(*name, |_| green)]
+        }
+        Pat::Placeholder(name, many) => {
+            let bound = m.bindings.get(name)?;
+            if !many && bound.len() != 1 {
+                return None;
+            }
+            bound.iter().map(Red::green).collect()
+        }
+        Pat::Any | Pat::Named(_) | Pat::Token(None, _) => unreachable!(),
+    })
+}
+
+struct PatParser<'a> {
+    lexer: Lexer<'a>,
+    /// Rejects what a [`Template`] cannot build.
+    template: bool,
+}
+
+impl<'a> PatParser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            lexer: Lexer::new(text),
+            template: false,
+        }
+    }
+
+    fn template(mut self) -> Self {
+        self.template = true;
+        self
+    }
+
+    fn parse(mut self) -> Result<Pat, PatternError> {
+        self.lexer.skip_ws();
+        let start = self.lexer.pos;
+        let pat = self.pat()?;
+        if self.template && matches!(pat, Pat::Placeholder(_, true)) {
+            return Err(PatternError::new(
+                "Template root must be a single element",
+                start,
+            ));
+        }
+        self.lexer.skip_ws();
+        match self.lexer.peek() {
+            None => Ok(pat),
+            Some(_) => Err(self.lexer.error("Expected end of pattern")),
+        }
+    }
+
+    fn pat(&mut self) -> Result<Pat, PatternError> {
+        self.lexer.skip_ws();
+        let start = self.lexer.pos;
+        if self.lexer.eat('"') {
+            if self.template {
+                return Err(PatternError::new("Template token needs a name", start));
+            }
+            return Ok(Pat::Token(None, self.lexer.string()?));
+        }
+        if self.lexer.eat('$') {
+            let name = self.lexer.ident("");
+            if name.is_empty() {
+                return Err(self.lexer.error("Expected placeholder name"));
+            }
+            return Ok(Pat::Placeholder(name.into(), self.lexer.eat('*')));
+        }
+        let name = self.lexer.ident("");
+        if name.is_empty() {
+            return Err(self.lexer.error("Expected pattern"));
+        }
+        if name == "_" {
+            if self.template {
+                return Err(PatternError::new("Template cannot match anything", start));
+            }
+            return Ok(Pat::Any);
+        }
+        let name = Kind::try_new(name).map_err(|e| PatternError::new(e, start))?;
+        if self.lexer.eat(':') {
+            if !self.lexer.eat('"') {
+                return Err(self.lexer.error("Expected token value"));
+            }
+            return Ok(Pat::Token(Some(name), self.lexer.string()?));
+        }
+        if !self.lexer.eat('(') {
+            if self.template {
+                return Err(PatternError::new("Template cannot match anything", start));
+            }
+            return Ok(Pat::Named(name));
+        }
+        let mut children = vec![];
+        loop {
+            self.lexer.skip_ws();
+            if self.lexer.eat(')') {
+                return Ok(Pat::Node(name, children));
+            }
+            if self.lexer.peek().is_none() {
+                return Err(self.lexer.error("Expected `)`"));
+            }
+            children.push(self.pat()?);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn sexp(builder: &mut Cache) -> Red {
+        let tree = builder.node("List", |builder| {
+            vec![
+                builder.token("open", "("),
+                builder.node("Cons", |builder| {
+                    vec![
+                        builder.with_trivia("open", "", "(", " "),
+                        builder.with_trivia("atom", "", "car", " "),
+                        builder.with_trivia("dot", "", ".", " "),
+                        builder.with_trivia("atom", "", "xs", " /* tail */ "),
+                        builder.token("close", ")"),
+                    ]
+                }),
+                builder.with_trivia("atom", " ", "car", ""),
+                builder.token("close", ")"),
+            ]
+        });
+        Red::root(tree)
+    }
+
+    #[test]
+    fn find() {
+        let mut builder = Cache::default();
+        let root = sexp(&mut builder);
+
+        let pattern = Pattern::parse(r#"Cons("(" "car" "." $x ")")"#).unwrap();
+        let matches = pattern.find_all(&root);
+        assert_eq!(1, matches.len());
+        assert_eq!(
+            "xs",
+            matches[0]
+                .get("x")
+                .unwrap()
+                .green_ref()
+                .as_token()
+                .unwrap()
+                .value
+        );
+
+        let atoms = Pattern::parse(r#"atom:"car""#).unwrap().find_all(&root);
+        assert_eq!(2, atoms.len());
+
+        let list = Pattern::parse("List(open $items* close)").unwrap();
+        let m = list.matches(&root).unwrap();
+        assert_eq!(2, m.get_many("items").len());
+        assert!(Pattern::parse("List($a $a close)")
+            .unwrap()
+            .matches(&root)
+            .is_none());
+    }
+
+    #[test]
+    fn replace() {
+        let mut builder = Cache::default();
+        let root = sexp(&mut builder);
+
+        let pattern = Pattern::parse(r#"Cons("(" "car" "." $x ")")"#).unwrap();
+        let template = Template::parse(r#"Head(atom:"first" $x)"#).unwrap();
+        let new = pattern.replace_all(&mut builder, &root, &template);
+
+        assert_eq!("(firstxs /* tail */  car)", new.to_string());
+        let car = new.children().nth(2).unwrap();
+        assert!(car.ptr_eq(root.nth_child(2).unwrap().green_ref()));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Err(PatternError::new("Expected `)`", 7)),
+            Pattern::parse("Cons($x").map(|_| ())
+        );
+        assert_eq!(
+            Err(PatternError::new("Unterminated string", 4)),
+            Pattern::parse(r#""car"#).map(|_| ())
+        );
+        assert!(Template::parse("_").is_err());
+        assert_eq!(
+            Err(PatternError::new("Template token needs a name", 5)),
+            Template::parse(r#"Head("x")"#).map(|_| ())
+        );
+        assert_eq!(
+            Err(PatternError::new(
+                "Template root must be a single element",
+                1
+            )),
+            Template::parse(" $x*").map(|_| ())
+        );
+        assert!(Pattern::parse("List(\u{3000}open\u{a0}$x*)").is_ok());
+    }
+
+    #[test]
+    fn sequence_templates() {
+        let mut builder = Cache::default();
+        let root = sexp(&mut builder);
+
+        let list = Pattern::parse("List(open $items* close)").unwrap();
+        let m = list.matches(&root).unwrap();
+        let spliced = Template::parse(r#"Items(open:"[" $items* close:"]")"#).unwrap();
+        assert_eq!(
+            "[( car . xs /* tail */ ) car]",
+            spliced.instantiate(&mut builder, &m).unwrap().to_string()
+        );
+        let single = Template::parse("Items($items)").unwrap();
+        assert!(single.instantiate(&mut builder, &m).is_none());
+
+        let empty = Pattern::parse("Cons(open $xs* atom dot atom close)").unwrap();
+        let m = empty.find_all(&root).remove(0);
+        assert!(m.get_many("xs").is_empty());
+        assert!(Template::parse("$xs")
+            .unwrap()
+            .instantiate(&mut builder, &m)
+            .is_none());
+    }
+}
@@ -0,0 +1,182 @@
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::dag::Dag;
+use crate::{Cache, CachePolicy, Green, GreenData, GreenKind, Kind, Node, Token};
+
+impl Serialize for Kind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Kind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Kind::try_new(&name).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Green {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Dag::encode(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Green {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut builder = Cache::with_policy(CachePolicy::disabled());
+        Dag::deserialize(deserializer)?
+            .decode(&mut builder)
+            .map_err(D::Error::custom)
+    }
+}
+
+fn unwrap_root<E: serde::de::Error>(green: Green) -> Result<GreenData, E> {
+    green
+        .0
+        .try_unwrap()
+        .map_err(|_| E::custom("Root is referenced by its children"))
+}
+
+fn clone_kind(kind: &GreenKind) -> GreenKind {
+    match kind {
+        GreenKind::Node(Node { children }) => GreenKind::Node(Node {
+            children: children.clone(),
+        }),
+        GreenKind::Alias(child) => GreenKind::Alias(child.clone()),
+        GreenKind::Token(Token {
+            leading,
+            value,
+            trailing,
+        }) => GreenKind::Token(Token {
+            leading: leading.clone(),
+            value: value.clone(),
+            trailing: trailing.clone(),
+        }),
+    }
+}
+
+impl Serialize for GreenData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Green::new(GreenData {
+            name: self.name,
+            size: self.size,
+            kind: clone_kind(&self.kind),
+        })
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for GreenData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        unwrap_root(Green::deserialize(deserializer)?)
+    }
+}
+
+/// Serialized like a [`GreenData`] with an empty name.
+impl Serialize for GreenKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GreenData {
+            name: Kind::default(),
+            size: 0.into(),
+            kind: clone_kind(self),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for GreenKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(GreenData::deserialize(deserializer)?.kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::tree;
+    use crate::*;
+
+    /// The shared fixture twice, shared by pointer when `builder` interns nodes.
+    fn twice(builder: &mut Cache) -> Green {
+        builder.node("List", |builder| {
+            vec![tree(builder).green(), tree(builder).green()]
+        })
+    }
+
+    #[test]
+    fn roundtrip_keeps_sharing() {
+        let mut builder = Cache::default();
+        let green = twice(&mut builder);
+
+        let json = serde_json::to_value(&green).unwrap();
+        assert_eq!(14, json["entries"].as_array().unwrap().len());
+        assert_eq!(7, json["names"].as_array().unwrap().len());
+
+        let back: Green = serde_json::from_value(json).unwrap();
+        assert_eq!(green, back);
+        assert_eq!(green.to_string(), back.to_string());
+        let children = back.children().collect::<Vec<_>>();
+        assert!(children[0].ptr_eq(&children[1]));
+    }
+
+    #[test]
+    fn unshared_duplicates_are_merged() {
+        let mut builder = Cache::with_policy(CachePolicy::disabled());
+        let green = twice(&mut builder);
+
+        let json = serde_json::to_string(&green).unwrap();
+        let back: Green = serde_json::from_str(&json).unwrap();
+
+        let children = back.children().collect::<Vec<_>>();
+        assert!(children[0].ptr_eq(&children[1]));
+
+        let value =
+            |builder: &mut Cache| builder.alias("Value", |builder| builder.token("number", "1"));
+        let green = builder.node("List", |builder| vec![value(builder), value(builder)]);
+        assert!(!green
+            .children()
+            .next()
+            .unwrap()
+            .ptr_eq(&green.children().nth(1).unwrap()));
+
+        let json = serde_json::to_string(&green).unwrap();
+        let back: Green = serde_json::from_str(&json).unwrap();
+
+        let children = back.children().collect::<Vec<_>>();
+        assert!(children[0].ptr_eq(&children[1]));
+    }
+
+    #[test]
+    fn oversized_text() {
+        let mut builder = Cache::default();
+        let mut json = serde_json::to_value(builder.token("x", "x")).unwrap();
+        // Each node holds the previous one twice, doubling the text up to 2^33 bytes.
+        let entries = json["entries"].as_array_mut().unwrap();
+        for i in 1..34 {
+            entries.push(serde_json::json!({ "Node": { "name": 0, "children": [i - 1, i - 1] } }));
+        }
+        json["root"] = 33.into();
+
+        let error = serde_json::from_value::<Green>(json).unwrap_err();
+        assert_eq!("Text does not fit `TextSize`", error.to_string());
+    }
+
+    #[test]
+    fn kind_and_data() {
+        let mut builder = Cache::default();
+        let green = tree(&mut builder).green();
+
+        let json = serde_json::to_string(green.kind()).unwrap();
+        let kind: GreenKind = serde_json::from_str(&json).unwrap();
+        assert_eq!(green.kind(), &kind);
+
+        let json = serde_json::to_string(&*green.0).unwrap();
+        let data: GreenData = serde_json::from_str(&json).unwrap();
+        assert_eq!(*green.0, data);
+
+        let json = r#"{"names":["Root"],"entries":[{"Node":{"name":0,"children":[0]}}],"root":0}"#;
+        let error = serde_json::from_str::<Green>(json).unwrap_err();
+        assert!(error.to_string().contains("not a back-reference"));
+    }
+}
@@ -0,0 +1,291 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use smol_str::SmolStr;
+
+use crate::builder::{Interned, Interner};
+use crate::{
+    CachePolicy, CacheStats, Green, GreenBuilder, GreenBuilderExt, GreenData, Kind, Trivia,
+};
+
+const DEFAULT_SHARDS: usize = 32;
+
+/// [`Cache`](crate::Cache) which can be shared between threads.
+///
+/// Entries are split into shards by hash, each behind its own lock, so
+/// threads interning different elements rarely wait on each other.
+#[derive(Debug)]
+pub struct SharedCache {
+    shards: Box<[Mutex<Interner>]>,
+    policy: CachePolicy,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    evictions: AtomicUsize,
+}
+
+impl Default for SharedCache {
+    fn default() -> Self {
+        Self::with_policy(CachePolicy::default())
+    }
+}
+
+impl SharedCache {
+    pub fn with_policy(policy: CachePolicy) -> Self {
+        Self::with_shards(policy, DEFAULT_SHARDS)
+    }
+
+    pub fn with_shards(policy: CachePolicy, shards: usize) -> Self {
+        Self {
+            shards: (0..shards.max(1)).map(|_| Default::default()).collect(),
+            policy,
+            hits: Default::default(),
+            misses: Default::default(),
+            evictions: Default::default(),
+        }
+    }
+
+    pub fn policy(&self) -> &CachePolicy {
+        &self.policy
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: (0..self.shards.len()).map(|i| self.shard(i).len()).sum(),
+        }
+    }
+
+    /// Drops every entry that is no longer referenced by a live tree.
+    /// Returns the number of dropped entries.
+    pub fn collect(&self) -> usize {
+        let mut dropped = 0;
+        loop {
+            // Parent and children may live in different shards, so collect all
+            // of them until nothing changes.
+            let before = dropped;
+            for i in 0..self.shards.len() {
+                dropped += self.shard(i).collect();
+            }
+            if dropped == before {
+                break dropped;
+            }
+        }
+    }
+
+    pub fn node(&self, name: impl Into<Kind>, f: impl FnOnce(&Self) -> Vec<Green>) -> Green {
+        GreenBuilderExt::node(&mut { self }, name, |_| f(self))
+    }
+
+    pub fn alias<F, G>(&self, name: impl Into<Kind>, f: F) -> Green
+    where
+        F: FnOnce(&Self) -> G,
+        G: Into<Option<Green>>,
+    {
+        GreenBuilderExt::alias(&mut { self }, name, |_| f(self))
+    }
+
+    pub fn token(&self, name: impl Into<Kind>, value: impl Into<SmolStr>) -> Green {
+        GreenBuilderExt::token(&mut { self }, name, value)
+    }
+
+    pub fn with_trivia(
+        &self,
+        name: impl Into<Kind>,
+        leading: impl Into<Trivia>,
+        value: impl Into<SmolStr>,
+        trailing: impl Into<Trivia>,
+    ) -> Green {
+        GreenBuilderExt::with_trivia(&mut { self }, name, leading, value, trailing)
+    }
+}
+
+impl SharedCache {
+    fn shard(&self, index: usize) -> MutexGuard<'_, Interner> {
+        self.shards[index]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn add_node(&self, node: GreenData) -> Green {
+        if !self.policy.allows(&node.kind) {
+            return Green::new(node);
+        }
+
+        let hash = Interner::hash(&self.policy, &node);
+        // High bits pick the shard, low bits the bucket inside it.
+        let shards = self.shards.len();
+        let index = ((u128::from(hash) * shards as u128) >> 64) as usize;
+        // Splits `max_entries` so that the shards never hold more in total.
+        let limit = self
+            .policy
+            .max_entries
+            .map(|max| max / shards + usize::from(index < max % shards));
+
+        match self
+            .shard(index)
+            .intern(&self.policy, limit, hash, node, Green::new)
+        {
+            Interned::Hit(green) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                green
+            }
+            Interned::Miss { green, evicted } => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                self.evictions.fetch_add(evicted, Ordering::Relaxed);
+                green
+            }
+        }
+    }
+}
+
+impl GreenBuilder for &SharedCache {
+    fn add_node(&mut self, node: GreenData) -> Green {
+        SharedCache::add_node(self, node)
+    }
+}
+
+impl GreenBuilder for Arc<SharedCache> {
+    fn add_node(&mut self, node: GreenData) -> Green {
+        SharedCache::add_node(self, node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::aliased;
+    use crate::*;
+
+    fn list(builder: &SharedCache, len: usize) -> Green {
+        builder.node("List", |builder| {
+            (0..len)
+                .map(|i| {
+                    builder.alias("Value", |builder| {
+                        builder.with_trivia("number", "", (i % 3).to_string(), " ")
+                    })
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let builder = SharedCache::default();
+        let roots = std::thread::scope(|scope| {
+            let handles = (0..4)
+                .map(|_| scope.spawn(|| list(&builder, 100)))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        for root in &roots {
+            let children = root.children().collect::<Vec<_>>();
+            assert!(children[0].ptr_eq(&children[3]));
+            assert!(children[1].ptr_eq(&roots[0].children().nth(4).unwrap()));
+        }
+
+        let stats = builder.stats();
+        assert_eq!(6, stats.entries);
+        assert_eq!(4 * 200, stats.hits + stats.misses);
+        assert_eq!(6, stats.misses);
+    }
+
+    #[test]
+    fn policy_and_collect() {
+        let builder = SharedCache::with_shards(CachePolicy::tokens_only(), 4);
+        let root = list(&builder, 10);
+        let children = root.children().collect::<Vec<_>>();
+        assert!(!children[0].ptr_eq(&children[3]));
+        assert!(aliased(&children[0]).ptr_eq(aliased(&children[3])));
+        assert_eq!(3, builder.stats().entries);
+
+        drop((root, children));
+        assert_eq!(3, builder.collect());
+        assert_eq!(0, builder.stats().entries);
+
+        let builder = SharedCache::with_shards(CachePolicy::default().with_max_entries(4), 1);
+        let first = list(&builder, 3);
+        drop(first);
+        let _second = builder.node("Other", |builder| {
+            vec![builder.token("a", "a"), builder.token("b", "b")]
+        });
+        let stats = builder.stats();
+        assert!(stats.evictions > 0);
+        assert!(stats.entries <= 4);
+    }
+
+    #[test]
+    fn max_entries_split_between_shards() {
+        let builder = SharedCache::with_shards(CachePolicy::default().with_max_entries(5), 4);
+        let tokens = (0..40)
+            .map(|i| builder.token("number", i.to_string()))
+            .collect::<Vec<_>>();
+
+        let stats = builder.stats();
+        assert!(stats.entries <= 5);
+        assert_eq!(40, stats.misses);
+        drop(tokens);
+    }
+
+    #[test]
+    fn generic_builders() {
+        fn pair<B: GreenBuilder>(builder: &mut B) -> Green {
+            builder.node("Pair", |builder| {
+                let key = builder.with_trivia("key", "", "a", " ");
+                vec![
+                    key,
+                    builder.alias("Value", |builder| builder.token("number", "1")),
+                ]
+            })
+        }
+
+        let shared = SharedCache::default();
+        let green = pair(&mut &shared);
+        assert_eq!(green, pair(&mut Cache::default()));
+        assert!(green.ptr_eq(&pair(&mut &shared)));
+
+        let one = aliased(&green.children().nth(1).unwrap()).clone();
+        let empty = GreenBuilderExt::try_replace_children(&mut &shared, green, vec![]);
+        assert_eq!("", empty.unwrap().to_string());
+        assert_eq!(
+            Err(MutationError::NotANode {
+                name: Kind::new("number")
+            }),
+            GreenBuilderExt::try_replace_children(&mut &shared, one, vec![])
+        );
+    }
+
+    #[test]
+    fn mutations_and_editor() {
+        let shared = SharedCache::default();
+        let mut builder = &shared;
+        let root = Red::root(list(&shared, 3));
+        let one = root.nth_child(1).unwrap();
+        let zero = shared.alias("Value", |builder| {
+            builder.with_trivia("number", "", "0", " ")
+        });
+
+        let replaced = one.replace(&mut builder, zero.clone());
+        assert_eq!("0 0 2 ", replaced.to_string());
+        assert!(replaced.children().next().unwrap().ptr_eq(&zero));
+
+        let mut editor = Editor::new(root.clone());
+        editor.remove(&one).unwrap();
+        editor
+            .push_many(&root, vec![builder.token("number", "3")])
+            .unwrap();
+        let edited = editor.finish(&mut builder);
+        assert_eq!("0 2 3", edited.root().green().to_string());
+        assert!(edited
+            .root()
+            .green()
+            .children()
+            .next()
+            .unwrap()
+            .ptr_eq(&zero));
+    }
+}
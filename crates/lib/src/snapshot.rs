@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+use smol_str::SmolStr;
+
+use crate::dag::{Dag, Decoder, Entry};
+use crate::{Cache, Green, Kind, Trivia, TriviaKind, TriviaPiece};
+
+const MAGIC: &[u8; 4] = b"MTRE";
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+const NODE: u8 = 0;
+const ALIAS: u8 = 1;
+const ALIAS_EMPTY: u8 = 2;
+const TOKEN: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    ChecksumMismatch,
+    InvalidUtf8,
+    Invalid(String),
+    /// A table or string does not fit the `u32` lengths of the format.
+    TooLarge,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "Not a tree snapshot"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported snapshot version {}", version)
+            }
+            SnapshotError::Truncated => write!(f, "Snapshot is truncated"),
+            SnapshotError::ChecksumMismatch => write!(f, "Snapshot checksum does not match"),
+            SnapshotError::InvalidUtf8 => write!(f, "Snapshot contains invalid UTF-8"),
+            SnapshotError::Invalid(desc) => write!(f, "Invalid snapshot: {}", desc),
+            SnapshotError::TooLarge => write!(f, "Tree is too large for a snapshot"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// FNV-1a
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl Green {
+    /// Binary snapshot of the tree.
+    ///
+    /// Layout, all integers little endian:
+    /// * header - `MTRE` and `u16` version, `u16` reserved,
+    /// * string table - `u32` count, then `u32` length and UTF-8 bytes per string,
+    /// * name table - `u32` count, then `u32` string index per name,
+    /// * node table - `u32` count, then entries referring to earlier entries only,
+    /// * `u32` root entry and `u64` FNV-1a checksum of everything before it.
+    ///
+    /// Shared and structurally identical subtrees are stored once.
+    pub fn to_snapshot(&self) -> Result<Vec<u8>, SnapshotError> {
+        let dag = Dag::encode(self);
+        let mut writer = Writer::default();
+        let names = dag
+            .names
+            .iter()
+            .map(|name| writer.string(name.name()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut entries = vec![];
+        for entry in &dag.entries {
+            writer.entry(&mut entries, entry)?;
+        }
+
+        let mut out = Vec::with_capacity(entries.len() + writer.bytes_len + 64);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        push_u32(&mut out, writer.strings.len())?;
+        for string in &writer.strings {
+            push_u32(&mut out, string.len())?;
+            out.extend_from_slice(string.as_bytes());
+        }
+        push_u32(&mut out, names.len())?;
+        for name in names {
+            out.extend_from_slice(&name.to_le_bytes());
+        }
+        push_u32(&mut out, dag.entries.len())?;
+        out.extend_from_slice(&entries);
+        out.extend_from_slice(&dag.root.to_le_bytes());
+        let sum = checksum(&out);
+        out.extend_from_slice(&sum.to_le_bytes());
+        Ok(out)
+    }
+}
+
+impl Cache {
+    /// Loads a tree written by [`Green::to_snapshot`], interning it like any other built tree.
+    pub fn load_snapshot(&mut self, bytes: &[u8]) -> Result<Green, SnapshotError> {
+        if bytes.len() < 8 {
+            return Err(SnapshotError::Truncated);
+        }
+        if &bytes[..4] != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        if bytes.len() < 16 {
+            return Err(SnapshotError::Truncated);
+        }
+        let (body, sum) = bytes.split_at(bytes.len() - 8);
+        let mut sum_bytes = [0; 8];
+        sum_bytes.copy_from_slice(sum);
+        if checksum(body) != u64::from_le_bytes(sum_bytes) {
+            return Err(SnapshotError::ChecksumMismatch);
+        }
+
+        let mut reader = Reader {
+            bytes: body,
+            pos: 8,
+        };
+        let count = reader.len()?;
+        let mut strings = Vec::with_capacity(count);
+        for _ in 0..count {
+            let len = reader.len()?;
+            let string =
+                std::str::from_utf8(reader.take(len)?).map_err(|_| SnapshotError::InvalidUtf8)?;
+            strings.push(string);
+        }
+        let string = |id: u32| {
+            strings
+                .get(id as usize)
+                .copied()
+                .ok_or_else(|| SnapshotError::Invalid(format!("Unknown string {}", id)))
+        };
+
+        let count = reader.len()?;
+        let mut names = Vec::with_capacity(count);
+        for _ in 0..count {
+            names.push(kind(string(reader.u32()?)?)?);
+        }
+
+        // Greens are built while reading, strings are borrowed from `bytes` until
+        // interned.
+        let count = reader.len()?;
+        let mut decoder = Decoder::new(self, names, count);
+        for _ in 0..count {
+            let tag = reader.u8()?;
+            let name = reader.u32()?;
+            let child =
+                |id: u32, decoder: &Decoder| decoder.child(id).map_err(SnapshotError::Invalid);
+            match tag {
+                NODE => {
+                    let len = reader.len()?;
+                    let children = (0..len)
+                        .map(|_| child(reader.u32()?, &decoder))
+                        .collect::<Result<_, _>>()?;
+                    decoder.node(name, children)
+                }
+                ALIAS => {
+                    let child = child(reader.u32()?, &decoder)?;
+                    decoder.alias(name, Some(child))
+                }
+                ALIAS_EMPTY => decoder.alias(name, None),
+                TOKEN => {
+                    let leading = reader.trivia(&string)?;
+                    let value = string(reader.u32()?)?;
+                    let trailing = reader.trivia(&string)?;
+                    decoder.token(name, leading, value, trailing)
+                }
+                tag => return Err(SnapshotError::Invalid(format!("Unknown tag {}", tag))),
+            }
+            .map_err(SnapshotError::Invalid)?;
+        }
+        let root = reader.u32()?;
+        if reader.pos != body.len() {
+            return Err(SnapshotError::Invalid("Trailing bytes".into()));
+        }
+
+        decoder.finish(root).map_err(SnapshotError::Invalid)
+    }
+}
+
+fn push_u32(out: &mut Vec<u8>, value: usize) -> Result<(), SnapshotError> {
+    let value = u32::try_from(value).map_err(|_| SnapshotError::TooLarge)?;
+    out.extend_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+fn kind(name: &str) -> Result<Kind, SnapshotError> {
+    Kind::try_new(name).map_err(|e| SnapshotError::Invalid(e.to_string()))
+}
+
+#[derive(Default)]
+struct Writer {
+    strings: Vec<SmolStr>,
+    ids: HashMap<SmolStr, u32>,
+    bytes_len: usize,
+}
+
+impl Writer {
+    fn string(&mut self, string: &str) -> Result<u32, SnapshotError> {
+        if let Some(id) = self.ids.get(string) {
+            return Ok(*id);
+        }
+        let id = u32::try_from(self.strings.len()).map_err(|_| SnapshotError::TooLarge)?;
+        self.bytes_len += string.len() + 4;
+        self.strings.push(string.into());
+        self.ids.insert(string.into(), id);
+        Ok(id)
+    }
+
+    fn entry(&mut self, out: &mut Vec<u8>, entry: &Entry) -> Result<(), SnapshotError> {
+        match entry {
+            Entry::Node { name, children } => {
+                out.push(NODE);
+                out.extend_from_slice(&name.to_le_bytes());
+                push_u32(out, children.len())?;
+                for child in children {
+                    out.extend_from_slice(&child.to_le_bytes());
+                }
+            }
+            Entry::Alias { name, child } => {
+                out.push(if child.is_some() { ALIAS } else { ALIAS_EMPTY });
+                out.extend_from_slice(&name.to_le_bytes());
+                if let Some(child) = child {
+                    out.extend_from_slice(&child.to_le_bytes());
+                }
+            }
+            Entry::Token {
+                name,
+                leading,
+                value,
+                trailing,
+            } => {
+                out.push(TOKEN);
+                out.extend_from_slice(&name.to_le_bytes());
+                self.trivia(out, leading)?;
+                let value = self.string(value)?;
+                out.extend_from_slice(&value.to_le_bytes());
+                self.trivia(out, trailing)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `u32` count, then a kind tag, optional kind name and text per piece.
+    fn trivia(&mut self, out: &mut Vec<u8>, trivia: &Trivia) -> Result<(), SnapshotError> {
+        push_u32(out, trivia.pieces().len())?;
+        for piece in trivia.pieces() {
+            match piece.kind {
+                TriviaKind::Whitespace => out.push(0),
+                TriviaKind::Newline => out.push(1),
+                TriviaKind::LineComment => out.push(2),
+                TriviaKind::BlockComment => out.push(3),
+                TriviaKind::Other(name) => {
+                    out.push(4);
+                    let name = self.string(name.name())?;
+                    out.extend_from_slice(&name.to_le_bytes());
+                }
+            }
+            let text = self.string(&piece.text)?;
+            out.extend_from_slice(&text.to_le_bytes());
+        }
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or(SnapshotError::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    /// Count of following items, checked against the remaining bytes.
+    fn len(&mut self) -> Result<usize, SnapshotError> {
+        let len = self.u32()? as usize;
+        if len > self.bytes.len() - self.pos {
+            return Err(SnapshotError::Truncated);
+        }
+        Ok(len)
+    }
+
+    fn trivia(
+        &mut self,
+        string: &impl Fn(u32) -> Result<&'a str, SnapshotError>,
+    ) -> Result<Trivia, SnapshotError> {
+        let len = self.len()?;
+        let mut pieces = Vec::with_capacity(len);
+        for _ in 0..len {
+            let kind = match self.u8()? {
+                0 => TriviaKind::Whitespace,
+                1 => TriviaKind::Newline,
+                2 => TriviaKind::LineComment,
+                3 => TriviaKind::BlockComment,
+                4 => TriviaKind::Other(kind(string(self.u32()?)?)?),
+                tag => return Err(SnapshotError::Invalid(format!("Unknown trivia {}", tag))),
+            };
+            pieces.push(TriviaPiece::new(kind, string(self.u32()?)?));
+        }
+        Ok(Trivia::new(pieces))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::tree;
+    use crate::*;
+
+    #[test]
+    fn roundtrip() {
+        let mut builder = Cache::default();
+        let comment = Trivia::new(vec![TriviaPiece::new(TriviaKind::LineComment, "// six\n")]);
+        let green = builder.node("Root", |builder| {
+            vec![
+                tree(builder).green(),
+                builder.with_trivia("number", comment, "6", "\n"),
+                builder.alias("Empty", |_| None),
+            ]
+        });
+
+        let bytes = green.to_snapshot().unwrap();
+        let back = builder.load_snapshot(&bytes).unwrap();
+        assert!(back.ptr_eq(&green));
+
+        let mut other = Cache::default();
+        let back = other.load_snapshot(&bytes).unwrap();
+        assert_eq!(green, back);
+        assert_eq!("1 + 2+3,45// six\n6\n", back.to_string());
+        assert!(other.load_snapshot(&bytes).unwrap().ptr_eq(&back));
+    }
+
+    #[test]
+    fn corrupted() {
+        let mut builder = Cache::default();
+        let bytes = tree(&mut builder).green().to_snapshot().unwrap();
+
+        let mut flipped = bytes.clone();
+        flipped[20] ^= 1;
+        assert_eq!(
+            Err(SnapshotError::ChecksumMismatch),
+            builder.load_snapshot(&flipped)
+        );
+
+        let mut version = bytes.clone();
+        version[4] = 9;
+        assert_eq!(
+            Err(SnapshotError::UnsupportedVersion(9)),
+            builder.load_snapshot(&version)
+        );
+
+        assert_eq!(
+            Err(SnapshotError::BadMagic),
+            builder.load_snapshot(b"JSON{}{}{}{}")
+        );
+        assert_eq!(
+            Err(SnapshotError::Truncated),
+            builder.load_snapshot(&bytes[..6])
+        );
+    }
+
+    #[test]
+    fn oversized_text() {
+        let u32 = |value: u32| value.to_le_bytes();
+        let mut bytes = b"MTRE\x01\x00\x00\x00".to_vec();
+        // One string `x`, used as a name and as a token value.
+        bytes.extend(u32(1).iter().chain(&u32(1)).chain(b"x"));
+        bytes.extend(u32(1).iter().chain(&u32(0)));
+        bytes.extend(&u32(34));
+        bytes.push(3);
+        bytes.extend(u32(0).iter().chain(&u32(0)).chain(&u32(0)).chain(&u32(0)));
+        // Each node holds the previous one twice, doubling the text up to 2^33 bytes.
+        for i in 1..34 {
+            bytes.push(0);
+            bytes.extend(
+                u32(0)
+                    .iter()
+                    .chain(&u32(2))
+                    .chain(&u32(i - 1))
+                    .chain(&u32(i - 1)),
+            );
+        }
+        bytes.extend(&u32(33));
+        let sum = super::checksum(&bytes);
+        bytes.extend(&sum.to_le_bytes());
+
+        let mut builder = Cache::default();
+        assert_eq!(
+            Err(SnapshotError::Invalid(
+                "Text does not fit `TextSize`".into()
+            )),
+            builder.load_snapshot(&bytes)
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use smol_str::SmolStr;
+use text_size::{TextRange, TextSize};
+
+use crate::DiffOp;
+
+/// Replacement of `range` in the old text with `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: TextRange,
+    pub text: SmolStr,
+}
+
+impl TextEdit {
+    pub fn replace(range: TextRange, text: impl Into<SmolStr>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+
+    pub fn insert(offset: TextSize, text: impl Into<SmolStr>) -> Self {
+        Self::replace(TextRange::empty(offset), text)
+    }
+
+    pub fn delete(range: TextRange) -> Self {
+        Self::replace(range, "")
+    }
+
+    pub fn apply(&self, text: &mut String) {
+        text.replace_range(std::ops::Range::<usize>::from(self.range), &self.text);
+    }
+
+    /// Applies edits sorted by their position in the old text.
+    pub fn apply_all(edits: &[TextEdit], text: &mut String) {
+        edits.iter().rev().for_each(|edit| edit.apply(text));
+    }
+}
+
+impl DiffOp {
+    pub fn text_edit(&self) -> TextEdit {
+        match self {
+            DiffOp::Insert { offset, green, .. } => TextEdit::insert(*offset, green.to_string()),
+            DiffOp::Delete { range, .. } => TextEdit::delete(*range),
+            DiffOp::Replace { range, new, .. } => TextEdit::replace(*range, new.to_string()),
+        }
+    }
+}
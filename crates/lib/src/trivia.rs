@@ -0,0 +1,203 @@
+use std::fmt::Display;
+
+use smol_str::SmolStr;
+use text_size::{TextLen, TextSize};
+
+use crate::{kind, Kind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    LineComment,
+    BlockComment,
+    Other(Kind),
+}
+
+impl TriviaKind {
+    pub fn name(self) -> Kind {
+        match self {
+            Self::Whitespace => kind!("whitespace"),
+            Self::Newline => kind!("newline"),
+            Self::LineComment => kind!("line_comment"),
+            Self::BlockComment => kind!("block_comment"),
+            Self::Other(name) => name,
+        }
+    }
+
+    pub fn is_comment(self) -> bool {
+        matches!(self, Self::LineComment | Self::BlockComment)
+    }
+}
+
+impl From<Kind> for TriviaKind {
+    fn from(name: Kind) -> Self {
+        [
+            Self::Whitespace,
+            Self::Newline,
+            Self::LineComment,
+            Self::BlockComment,
+        ]
+        .iter()
+        .copied()
+        .find(|kind| kind.name() == name)
+        .unwrap_or(Self::Other(name))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TriviaPiece {
+    pub kind: TriviaKind,
+    pub text: SmolStr,
+}
+
+impl TriviaPiece {
+    pub fn new(kind: impl Into<TriviaKind>, text: impl Into<SmolStr>) -> Self {
+        Self {
+            kind: kind.into(),
+            text: text.into(),
+        }
+    }
+
+    pub fn text_len(&self) -> TextSize {
+        self.text.as_str().text_len()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trivia {
+    pieces: Vec<TriviaPiece>,
+}
+
+impl Trivia {
+    pub fn new(pieces: Vec<TriviaPiece>) -> Self {
+        Self { pieces }
+    }
+
+    pub fn pieces(&self) -> &[TriviaPiece] {
+        &self.pieces
+    }
+
+    pub fn comments(&self) -> impl Iterator<Item = &TriviaPiece> {
+        self.pieces.iter().filter(|piece| piece.kind.is_comment())
+    }
+
+    pub fn push(&mut self, piece: TriviaPiece) {
+        self.pieces.push(piece);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pieces.iter().all(|piece| piece.text.is_empty())
+    }
+
+    pub fn text_len(&self) -> TextSize {
+        self.pieces.iter().map(TriviaPiece::text_len).sum()
+    }
+}
+
+impl Display for Trivia {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.pieces
+            .iter()
+            .try_for_each(|piece| write!(f, "{}", piece.text))
+    }
+}
+
+/// Splits plain text into whitespace and newline pieces.
+/// Anything else ends up in a single `trivia` piece per run.
+impl From<&str> for Trivia {
+    fn from(text: &str) -> Self {
+        fn class(c: char) -> TriviaKind {
+            match c {
+                '\n' | '\r' => TriviaKind::Newline,
+                c if c.is_whitespace() => TriviaKind::Whitespace,
+                _ => TriviaKind::Other(kind!("trivia")),
+            }
+        }
+
+        let mut pieces = vec![];
+        let mut start = 0;
+        let mut current = None;
+        for (idx, c) in text.char_indices() {
+            let kind = class(c);
+            match current {
+                Some(current) if current == kind => continue,
+                Some(current) => {
+                    pieces.push(TriviaPiece::new(current, &text[start..idx]));
+                    start = idx;
+                }
+                None => (),
+            }
+            current = Some(kind);
+        }
+        if let Some(current) = current {
+            pieces.push(TriviaPiece::new(current, &text[start..]));
+        }
+        Self { pieces }
+    }
+}
+
+impl From<SmolStr> for Trivia {
+    fn from(text: SmolStr) -> Self {
+        Self::from(text.as_str())
+    }
+}
+
+impl From<String> for Trivia {
+    fn from(text: String) -> Self {
+        Self::from(text.as_str())
+    }
+}
+
+impl From<Vec<TriviaPiece>> for Trivia {
+    fn from(pieces: Vec<TriviaPiece>) -> Self {
+        Self { pieces }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn from_str() {
+        let trivia = Trivia::from("  \n\n\t#x ");
+        let pieces = trivia
+            .pieces()
+            .iter()
+            .map(|piece| (piece.kind.name(), piece.text.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                (Kind::new("whitespace"), "  "),
+                (Kind::new("newline"), "\n\n"),
+                (Kind::new("whitespace"), "\t"),
+                (Kind::new("trivia"), "#x"),
+                (Kind::new("whitespace"), " "),
+            ],
+            pieces
+        );
+        assert_eq!("  \n\n\t#x ", trivia.to_string());
+        assert_eq!(TextSize::from(8), trivia.text_len());
+    }
+
+    #[test]
+    fn comments() {
+        let trivia = Trivia::new(vec![
+            TriviaPiece::new(TriviaKind::LineComment, "// doc"),
+            TriviaPiece::new(TriviaKind::Newline, "\n"),
+            TriviaPiece::new(Kind::new("block_comment"), "/* x */"),
+        ]);
+
+        let comments = trivia
+            .comments()
+            .map(|piece| piece.text.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec!["// doc", "/* x */"], comments);
+    }
+}
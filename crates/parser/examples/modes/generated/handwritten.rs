@@ -7,7 +7,7 @@ use smol_str::SmolStr;
 pub struct Atom(Red);
 impl Ast for Atom {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("atom") {
+        if !node.is(kind!("atom")) {
             return None;
         }
         node.green().as_token()?;
@@ -21,13 +21,13 @@ impl Ast for Atom {
 
 impl Atom {
     pub fn build(value: impl Into<SmolStr>) -> TokenBuilder<Atom> {
-        TokenBuilder::custom("atom", value)
+        TokenBuilder::custom(kind!("atom"), value)
     }
 }
 
 impl IntoBuilder<Value> for TokenBuilder<Atom> {
     fn into_builder(self) -> AliasBuilder<Self, Value> {
-        AliasBuilder::new("Value", self)
+        AliasBuilder::new(kind!("Value"), self)
     }
 }
 
@@ -35,7 +35,7 @@ impl IntoBuilder<Value> for TokenBuilder<Atom> {
 pub struct Text(Red);
 impl Ast for Text {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("text") {
+        if !node.is(kind!("text")) {
             return None;
         }
         node.green().as_token()?;
@@ -49,12 +49,12 @@ impl Ast for Text {
 
 impl Text {
     pub fn build(value: impl Into<SmolStr>) -> TokenBuilder<Text> {
-        TokenBuilder::custom("text", value)
+        TokenBuilder::custom(kind!("text"), value)
     }
 }
 
 impl IntoBuilder<StrValue> for TokenBuilder<Text> {
     fn into_builder(self) -> AliasBuilder<Self, StrValue> {
-        AliasBuilder::new("StrValue", self)
+        AliasBuilder::new(kind!("StrValue"), self)
     }
 }
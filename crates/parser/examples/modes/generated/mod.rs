@@ -1,6 +1,8 @@
 #![allow(clippy::redundant_clone, clippy::wrong_self_convention)]
 #![allow(dead_code)]
-use microtree::{AliasBuilder, Ast, AstBuilder, Cache, Green, IntoBuilder, Red, TokenBuilder};
+use microtree::{
+    kind, AliasBuilder, Ast, AstBuilder, Green, GreenBuilder, IntoBuilder, Red, TokenBuilder,
+};
 
 mod handwritten;
 pub use handwritten::*;
@@ -9,7 +11,7 @@ pub use handwritten::*;
 pub struct DQuote(Red);
 impl Ast for DQuote {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -33,7 +35,7 @@ impl DQuote {
 pub struct OpenI(Red);
 impl Ast for OpenI {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -57,7 +59,7 @@ impl OpenI {
 pub struct OpenP(Red);
 impl Ast for OpenP {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -81,7 +83,7 @@ impl OpenP {
 pub struct CloseP(Red);
 impl Ast for CloseP {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -105,7 +107,7 @@ impl CloseP {
 pub struct Dot(Red);
 impl Ast for Dot {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -129,7 +131,7 @@ impl Dot {
 pub struct CloseI(Red);
 impl Ast for CloseI {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -279,7 +281,7 @@ impl StrValue {
 pub struct Nil(Red);
 impl Ast for Nil {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("Nil") {
+        if !node.is(kind!("Nil")) {
             return None;
         }
         node.green().as_node()?;
@@ -342,20 +344,20 @@ where
     T1: AstBuilder<T = CloseP>,
 {
     type T = Nil;
-    fn build(self, builder: &mut Cache) -> Nil {
+    fn build(self, builder: &mut dyn GreenBuilder) -> Nil {
         let green = AstBuilder::build_green(self, builder);
         Nil::new(Red::root(green)).unwrap()
     }
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
         AstBuilder::build_green(*self, builder)
     }
-    fn build_green(self, builder: &mut Cache) -> Green {
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
         let children = None
             .into_iter()
             .chain(self.open_p.map(|it| it.build_green(builder)).into_iter())
             .chain(self.close_p.map(|it| it.build_green(builder)).into_iter())
             .collect();
-        builder.node("Nil", move |_| children)
+        builder.node(kind!("Nil"), move |_| children)
     }
 }
 impl<T0, T1> IntoBuilder<Value> for NilBuilder<T0, T1>
@@ -364,7 +366,7 @@ where
     T1: AstBuilder<T = CloseP>,
 {
     fn into_builder(self) -> AliasBuilder<Self, Value> {
-        AliasBuilder::new("Value", self)
+        AliasBuilder::new(kind!("Value"), self)
     }
 }
 
@@ -372,7 +374,7 @@ where
 pub struct List(Red);
 impl Ast for List {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("List") {
+        if !node.is(kind!("List")) {
             return None;
         }
         node.green().as_node()?;
@@ -446,14 +448,14 @@ where
     T2: AstBuilder<T = CloseP>,
 {
     type T = List;
-    fn build(self, builder: &mut Cache) -> List {
+    fn build(self, builder: &mut dyn GreenBuilder) -> List {
         let green = AstBuilder::build_green(self, builder);
         List::new(Red::root(green)).unwrap()
     }
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
         AstBuilder::build_green(*self, builder)
     }
-    fn build_green(self, builder: &mut Cache) -> Green {
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
         let children = None
             .into_iter()
             .chain(self.open_p.map(|it| it.build_green(builder)).into_iter())
@@ -465,7 +467,7 @@ where
             })
             .chain(self.close_p.map(|it| it.build_green(builder)).into_iter())
             .collect();
-        builder.node("List", move |_| children)
+        builder.node(kind!("List"), move |_| children)
     }
 }
 impl<T0, T2> IntoBuilder<Value> for ListBuilder<T0, T2>
@@ -474,7 +476,7 @@ where
     T2: AstBuilder<T = CloseP>,
 {
     fn into_builder(self) -> AliasBuilder<Self, Value> {
-        AliasBuilder::new("Value", self)
+        AliasBuilder::new(kind!("Value"), self)
     }
 }
 
@@ -482,7 +484,7 @@ where
 pub struct Cons(Red);
 impl Ast for Cons {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("Cons") {
+        if !node.is(kind!("Cons")) {
             return None;
         }
         node.green().as_node()?;
@@ -578,14 +580,14 @@ where
     T4: AstBuilder<T = CloseP>,
 {
     type T = Cons;
-    fn build(self, builder: &mut Cache) -> Cons {
+    fn build(self, builder: &mut dyn GreenBuilder) -> Cons {
         let green = AstBuilder::build_green(self, builder);
         Cons::new(Red::root(green)).unwrap()
     }
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
         AstBuilder::build_green(*self, builder)
     }
-    fn build_green(self, builder: &mut Cache) -> Green {
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
         let children = None
             .into_iter()
             .chain(self.open_p.map(|it| it.build_green(builder)).into_iter())
@@ -594,7 +596,7 @@ where
             .chain(self.cdr.map(|it| it.build_green(builder)).into_iter())
             .chain(self.close_p.map(|it| it.build_green(builder)).into_iter())
             .collect();
-        builder.node("Cons", move |_| children)
+        builder.node(kind!("Cons"), move |_| children)
     }
 }
 impl<T0, T1, T2, T3, T4> IntoBuilder<Value> for ConsBuilder<T0, T1, T2, T3, T4>
@@ -606,7 +608,7 @@ where
     T4: AstBuilder<T = CloseP>,
 {
     fn into_builder(self) -> AliasBuilder<Self, Value> {
-        AliasBuilder::new("Value", self)
+        AliasBuilder::new(kind!("Value"), self)
     }
 }
 
@@ -614,7 +616,7 @@ where
 pub struct String(Red);
 impl Ast for String {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("String") {
+        if !node.is(kind!("String")) {
             return None;
         }
         node.green().as_node()?;
@@ -688,14 +690,14 @@ where
     T2: AstBuilder<T = DQuote>,
 {
     type T = String;
-    fn build(self, builder: &mut Cache) -> String {
+    fn build(self, builder: &mut dyn GreenBuilder) -> String {
         let green = AstBuilder::build_green(self, builder);
         String::new(Red::root(green)).unwrap()
     }
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
         AstBuilder::build_green(*self, builder)
     }
-    fn build_green(self, builder: &mut Cache) -> Green {
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
         let children = None
             .into_iter()
             .chain(self.l_dquote.map(|it| it.build_green(builder)).into_iter())
@@ -707,7 +709,7 @@ where
             })
             .chain(self.r_dquote.map(|it| it.build_green(builder)).into_iter())
             .collect();
-        builder.node("String", move |_| children)
+        builder.node(kind!("String"), move |_| children)
     }
 }
 impl<T0, T2> IntoBuilder<Value> for StringBuilder<T0, T2>
@@ -716,7 +718,7 @@ where
     T2: AstBuilder<T = DQuote>,
 {
     fn into_builder(self) -> AliasBuilder<Self, Value> {
-        AliasBuilder::new("Value", self)
+        AliasBuilder::new(kind!("Value"), self)
     }
 }
 
@@ -724,7 +726,7 @@ where
 pub struct Interpolated(Red);
 impl Ast for Interpolated {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("Interpolated") {
+        if !node.is(kind!("Interpolated")) {
             return None;
         }
         node.green().as_node()?;
@@ -798,21 +800,21 @@ where
     T2: AstBuilder<T = CloseI>,
 {
     type T = Interpolated;
-    fn build(self, builder: &mut Cache) -> Interpolated {
+    fn build(self, builder: &mut dyn GreenBuilder) -> Interpolated {
         let green = AstBuilder::build_green(self, builder);
         Interpolated::new(Red::root(green)).unwrap()
     }
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
         AstBuilder::build_green(*self, builder)
     }
-    fn build_green(self, builder: &mut Cache) -> Green {
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
         let children = None
             .into_iter()
             .chain(self.open_i.map(|it| it.build_green(builder)).into_iter())
             .chain(self.value.map(|it| it.build_green(builder)).into_iter())
             .chain(self.close_i.map(|it| it.build_green(builder)).into_iter())
             .collect();
-        builder.node("Interpolated", move |_| children)
+        builder.node(kind!("Interpolated"), move |_| children)
     }
 }
 impl<T0, T1, T2> IntoBuilder<StrValue> for InterpolatedBuilder<T0, T1, T2>
@@ -822,6 +824,6 @@ where
     T2: AstBuilder<T = CloseI>,
 {
     fn into_builder(self) -> AliasBuilder<Self, StrValue> {
-        AliasBuilder::new("StrValue", self)
+        AliasBuilder::new(kind!("StrValue"), self)
     }
 }
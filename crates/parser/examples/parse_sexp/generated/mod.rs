@@ -1,6 +1,8 @@
 #![allow(clippy::redundant_clone, clippy::wrong_self_convention)]
 #![allow(dead_code)]
-use microtree::{AliasBuilder, Ast, AstBuilder, Cache, Green, IntoBuilder, Red, TokenBuilder};
+use microtree::{
+    kind, AliasBuilder, Ast, AstBuilder, Green, GreenBuilder, IntoBuilder, Red, TokenBuilder,
+};
 
 mod handwritten;
 pub use handwritten::*;
@@ -9,7 +11,7 @@ pub use handwritten::*;
 pub struct LParen(Red);
 impl Ast for LParen {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -33,7 +35,7 @@ impl LParen {
 pub struct RParen(Red);
 impl Ast for RParen {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -57,7 +59,7 @@ impl RParen {
 pub struct Dot(Red);
 impl Ast for Dot {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("token") {
+        if !node.is(kind!("token")) {
             return None;
         }
         let green = node.green();
@@ -151,7 +153,7 @@ impl Value {
 pub struct Nil(Red);
 impl Ast for Nil {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("Nil") {
+        if !node.is(kind!("Nil")) {
             return None;
         }
         node.green().as_node()?;
@@ -214,20 +216,20 @@ where
     T1: AstBuilder<T = RParen>,
 {
     type T = Nil;
-    fn build(self, builder: &mut Cache) -> Nil {
+    fn build(self, builder: &mut dyn GreenBuilder) -> Nil {
         let green = AstBuilder::build_green(self, builder);
         Nil::new(Red::root(green)).unwrap()
     }
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
         AstBuilder::build_green(*self, builder)
     }
-    fn build_green(self, builder: &mut Cache) -> Green {
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
         let children = None
             .into_iter()
             .chain(self.lparen.map(|it| it.build_green(builder)).into_iter())
             .chain(self.rparen.map(|it| it.build_green(builder)).into_iter())
             .collect();
-        builder.node("Nil", move |_| children)
+        builder.node(kind!("Nil"), move |_| children)
     }
 }
 impl<T0, T1> IntoBuilder<Value> for NilBuilder<T0, T1>
@@ -236,7 +238,7 @@ where
     T1: AstBuilder<T = RParen>,
 {
     fn into_builder(self) -> AliasBuilder<Self, Value> {
-        AliasBuilder::new("Value", self)
+        AliasBuilder::new(kind!("Value"), self)
     }
 }
 
@@ -244,7 +246,7 @@ where
 pub struct List(Red);
 impl Ast for List {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("List") {
+        if !node.is(kind!("List")) {
             return None;
         }
         node.green().as_node()?;
@@ -313,14 +315,14 @@ where
     T2: AstBuilder<T = RParen>,
 {
     type T = List;
-    fn build(self, builder: &mut Cache) -> List {
+    fn build(self, builder: &mut dyn GreenBuilder) -> List {
         let green = AstBuilder::build_green(self, builder);
         List::new(Red::root(green)).unwrap()
     }
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
         AstBuilder::build_green(*self, builder)
     }
-    fn build_green(self, builder: &mut Cache) -> Green {
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
         let children = None
             .into_iter()
             .chain(self.lparen.map(|it| it.build_green(builder)).into_iter())
@@ -332,7 +334,7 @@ where
             })
             .chain(self.rparen.map(|it| it.build_green(builder)).into_iter())
             .collect();
-        builder.node("List", move |_| children)
+        builder.node(kind!("List"), move |_| children)
     }
 }
 impl<T0, T2> IntoBuilder<Value> for ListBuilder<T0, T2>
@@ -341,7 +343,7 @@ where
     T2: AstBuilder<T = RParen>,
 {
     fn into_builder(self) -> AliasBuilder<Self, Value> {
-        AliasBuilder::new("Value", self)
+        AliasBuilder::new(kind!("Value"), self)
     }
 }
 
@@ -349,7 +351,7 @@ where
 pub struct Cons(Red);
 impl Ast for Cons {
     fn new(node: Red) -> Option<Self> {
-        if !node.is("Cons") {
+        if !node.is(kind!("Cons")) {
             return None;
         }
         node.green().as_node()?;
@@ -445,14 +447,14 @@ where
     T4: AstBuilder<T = RParen>,
 {
     type T = Cons;
-    fn build(self, builder: &mut Cache) -> Cons {
+    fn build(self, builder: &mut dyn GreenBuilder) -> Cons {
         let green = AstBuilder::build_green(self, builder);
         Cons::new(Red::root(green)).unwrap()
     }
-    fn build_boxed_green(self: Box<Self>, builder: &mut Cache) -> Green {
+    fn build_boxed_green(self: Box<Self>, builder: &mut dyn GreenBuilder) -> Green {
         AstBuilder::build_green(*self, builder)
     }
-    fn build_green(self, builder: &mut Cache) -> Green {
+    fn build_green(self, builder: &mut dyn GreenBuilder) -> Green {
         let children = None
             .into_iter()
             .chain(self.lparen.map(|it| it.build_green(builder)).into_iter())
@@ -461,7 +463,7 @@ where
             .chain(self.cdr.map(|it| it.build_green(builder)).into_iter())
             .chain(self.rparen.map(|it| it.build_green(builder)).into_iter())
             .collect();
-        builder.node("Cons", move |_| children)
+        builder.node(kind!("Cons"), move |_| children)
     }
 }
 impl<T0, T1, T2, T3, T4> IntoBuilder<Value> for ConsBuilder<T0, T1, T2, T3, T4>
@@ -473,6 +475,6 @@ where
     T4: AstBuilder<T = RParen>,
 {
     fn into_builder(self) -> AliasBuilder<Self, Value> {
-        AliasBuilder::new("Value", self)
+        AliasBuilder::new(kind!("Value"), self)
     }
 }
@@ -1,7 +1,5 @@
-use crate::{
-    Context, Error, OptionExt, Parser, PeekableIterator, SmolStr, State, TextRange, TokenKind,
-};
-use microtree::{Green, Name};
+use crate::{Context, Error, OptionExt, Parser, PeekableIterator, State, TextRange, TokenKind};
+use microtree::{kind, Green, Kind, Trivia, TriviaKind, TriviaPiece};
 use std::collections::BTreeSet;
 
 impl<Fun, Tok> Parser<Tok> for Fun
@@ -17,7 +15,7 @@ where
 pub struct Builder<'a, Tok: TokenKind> {
     pub(crate) state: State<Tok>,
     pub(crate) ctx: &'a Context<'a, Tok>,
-    pub(crate) names: BTreeSet<Name>,
+    pub(crate) names: BTreeSet<Kind>,
 }
 
 impl<'a, Tok: TokenKind> Builder<'a, Tok> {
@@ -28,8 +26,8 @@ impl<'a, Tok: TokenKind> Builder<'a, Tok> {
             names: Default::default(),
         }
     }
-    pub fn name(mut self, name: Name) -> Self {
-        self.names.insert(name);
+    pub fn name(mut self, name: impl Into<Kind>) -> Self {
+        self.names.insert(name.into());
         self
     }
     pub fn peek_token(&mut self) -> Option<Tok> {
@@ -64,7 +62,7 @@ impl<'a, Tok: TokenKind> Builder<'a, Tok> {
             node = match (node, alias) {
                 (None, _) => break None,
                 (node, None) => break node,
-                (Some(n), Some(alias)) => Some(state.cache().alias(alias, move |_| n)),
+                (Some(n), Some(alias)) => Some(state.cache().alias(alias, |_| n)),
             }
         };
 
@@ -74,7 +72,7 @@ impl<'a, Tok: TokenKind> Builder<'a, Tok> {
     pub fn handle_trivia(
         trivia: Option<&'a dyn Parser<Tok>>,
         state: State<Tok>,
-    ) -> (SmolStr, State<Tok>) {
+    ) -> (Trivia, State<Tok>) {
         match trivia {
             None => (Default::default(), state),
             Some(trivia) => {
@@ -83,13 +81,41 @@ impl<'a, Tok: TokenKind> Builder<'a, Tok> {
                     trivia.parse(state, &trivia_ctx)
                 };
                 (
-                    trivia.map(|t| t.to_string().into()).unwrap_or_default(),
+                    trivia.map(|t| Self::trivia_pieces(&t)).unwrap_or_default(),
                     state,
                 )
             }
         }
     }
 
+    /// Every token produced by the trivia parser becomes one piece named after
+    /// its outermost name. Unnamed whitespace is split into whitespace and newlines.
+    fn trivia_pieces(green: &Green) -> Trivia {
+        let mut trivia = Trivia::default();
+        let mut stack = vec![green.clone()];
+        while let Some(green) = stack.pop() {
+            match green.as_token() {
+                Some(_) => {
+                    let text = green.to_string();
+                    match TriviaKind::from(green.name()) {
+                        TriviaKind::Other(_) if text.trim().is_empty() => {
+                            Trivia::from(text)
+                                .pieces()
+                                .iter()
+                                .for_each(|piece| trivia.push(piece.clone()));
+                        }
+                        kind => trivia.push(TriviaPiece::new(kind, text)),
+                    }
+                }
+                None => {
+                    let children = green.children().collect::<Vec<_>>();
+                    stack.extend(children.into_iter().rev());
+                }
+            }
+        }
+        trivia
+    }
+
     pub fn error(self, desc: impl ToString) -> (Option<Green>, State<Tok>) {
         let Self {
             mut state, names, ..
@@ -106,7 +132,7 @@ impl<'a, Tok: TokenKind> Builder<'a, Tok> {
 
         state.add_error(error);
 
-        let mut node = state.cache().token("error", value);
+        let mut node = state.cache().token(kind!("error"), value);
         for alias in names {
             node = state.cache().alias(alias, |_| node);
         }
@@ -125,7 +151,9 @@ impl<'a, Tok: TokenKind> Builder<'a, Tok> {
         let (trailing, mut state) = Self::handle_trivia(ctx.trailing_trivia, state);
 
         let mut node = match value {
-            None => state.cache().with_trivia("eof", leading, "", trailing),
+            None => state
+                .cache()
+                .with_trivia(kind!("eof"), leading, "", trailing),
             Some(value) => {
                 let name = names.next().unwrap_or_default();
 
@@ -145,7 +173,7 @@ impl<'a, Tok: TokenKind> Builder<'a, Tok> {
 pub struct NodeBuilder<'a, Tok: TokenKind> {
     state: State<Tok>,
     ctx: &'a Context<'a, Tok>,
-    names: BTreeSet<Name>,
+    names: BTreeSet<Kind>,
     children: Vec<Green>,
 }
 
@@ -159,8 +187,8 @@ impl<'a, Tok: TokenKind> NodeBuilder<'a, Tok> {
         }
     }
 
-    pub fn name(mut self, name: Name) -> Self {
-        self.names.insert(name);
+    pub fn name(mut self, name: impl Into<Kind>) -> Self {
+        self.names.insert(name.into());
         self
     }
 
@@ -228,7 +256,7 @@ impl<'a, Tok: TokenKind> NodeBuilder<'a, Tok> {
         let name = names.next().unwrap_or_default();
         let aliases = names;
 
-        let mut node = state.cache().node(name, move |_| children);
+        let mut node = state.cache().node(name, |_| children);
         for alias in aliases {
             node = state.cache().alias(alias, |_| node);
         }
@@ -236,3 +264,103 @@ impl<'a, Tok: TokenKind> NodeBuilder<'a, Tok> {
         (Some(node), state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use microtree::{kind, TriviaKind, TriviaPiece};
+
+    use crate::{parsers::any_token, Builder, Context, Lexer, Parser, SmolStr, State, TokenKind};
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    enum Tok {
+        Word,
+        Whitespace,
+        Comment,
+    }
+
+    impl std::fmt::Display for Tok {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    impl TokenKind for Tok {
+        type Extra = ();
+
+        fn is_mergeable(self, _other: Self) -> bool {
+            false
+        }
+
+        fn lex(lexer: &mut Lexer<Self>) -> Option<(Self, SmolStr)> {
+            let input = lexer.input_mut();
+            let i = input.as_ref();
+            let peeked = i.chars().next()?;
+            let (kind, len) = if peeked.is_whitespace() {
+                (
+                    Tok::Whitespace,
+                    i.chars().take_while(|c| c.is_whitespace()).count(),
+                )
+            } else if peeked == '#' {
+                (Tok::Comment, i.chars().take_while(|c| *c != '\n').count())
+            } else {
+                (
+                    Tok::Word,
+                    i.chars().take_while(|c| c.is_alphanumeric()).count().max(1),
+                )
+            };
+            Some((kind, input.chomp(len)))
+        }
+    }
+
+    fn trivia() -> impl Parser<Tok> {
+        |builder: Builder<Tok>| {
+            let mut builder = builder.node();
+            loop {
+                builder = match builder.peek_token() {
+                    Some(Tok::Whitespace) => builder.parse(any_token()),
+                    Some(Tok::Comment) => {
+                        builder.parse(|builder: Builder<Tok>| builder.name("line_comment").token())
+                    }
+                    _ => break builder.finish(),
+                }
+            }
+        }
+    }
+
+    fn word() -> impl Parser<Tok> {
+        |builder: Builder<Tok>| {
+            let trivia = trivia();
+            let ctx = Context::new(&trivia);
+            builder.set_ctx(&ctx).name("word").token()
+        }
+    }
+
+    #[test]
+    fn trivia_pieces() {
+        let root = State::parse(Lexer::new("  # note\n\t word # end\n"), word())
+            .root
+            .unwrap();
+        let token = root.as_token().unwrap();
+
+        assert!(root.is("word"));
+        assert_eq!("word", token.value);
+        assert_eq!(
+            &[
+                TriviaPiece::new(TriviaKind::Whitespace, "  "),
+                TriviaPiece::new(TriviaKind::LineComment, "# note"),
+                TriviaPiece::new(TriviaKind::Newline, "\n"),
+                TriviaPiece::new(TriviaKind::Whitespace, "\t "),
+            ],
+            token.leading.pieces()
+        );
+        assert_eq!(
+            &[
+                TriviaPiece::new(TriviaKind::Whitespace, " "),
+                TriviaPiece::new(kind!("line_comment"), "# end"),
+                TriviaPiece::new(TriviaKind::Newline, "\n"),
+            ],
+            token.trailing.pieces()
+        );
+        assert_eq!(1, token.leading.comments().count());
+    }
+}
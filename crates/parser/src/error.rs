@@ -13,4 +13,12 @@ impl Error {
             loc,
         }
     }
+
+    pub fn desc(&self) -> &str {
+        &self.desc
+    }
+
+    pub fn loc(&self) -> TextRange {
+        self.loc
+    }
 }
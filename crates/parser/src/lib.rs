@@ -1,5 +1,5 @@
+pub use microtree::{TextLen, TextRange, TextSize};
 pub use smol_str::SmolStr;
-pub use text_size::{TextLen, TextRange, TextSize};
 
 mod builder;
 mod context;
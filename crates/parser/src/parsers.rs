@@ -1,7 +1,8 @@
 use crate::{Builder, Parser, TokenKind};
+use microtree::kind;
 
 pub fn any_token<Tok: TokenKind>() -> impl Parser<Tok> {
-    |builder: Builder<Tok>| builder.name("token").token()
+    |builder: Builder<Tok>| builder.name(kind!("token")).token()
 }
 
 pub fn error<Tok: TokenKind>(desc: impl ToString + Clone) -> impl Parser<Tok> {
@@ -16,7 +17,7 @@ pub fn tokens<Tok: TokenKind>(expected: &[Tok]) -> impl Parser<Tok> + '_ {
         (Some(tok), false) if !expected.contains(&tok) => {
             builder.error(format!("{} but found {}", Expected(expected), tok))
         }
-        _ => builder.name("token").token(),
+        _ => builder.name(kind!("token")).token(),
     }
 }
 
@@ -28,7 +29,7 @@ pub fn token<Tok: TokenKind>(expected: impl Into<Option<Tok>>) -> impl Parser<To
         (Some(tok), Some(expected)) if tok != expected => {
             builder.error(format!("Expected {} but found {}", expected, tok))
         }
-        _ => builder.name("token").token(),
+        _ => builder.name(kind!("token")).token(),
     }
 }
 
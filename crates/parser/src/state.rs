@@ -1,21 +1,27 @@
-use microtree::Cache;
+use std::sync::Arc;
+
+use microtree::{Cache, GreenBuilder, SharedCache};
 
 use crate::{Context, Error, Lexer, ParseResult, Parser, TokenKind};
 
 pub struct State<Tok: TokenKind> {
     lexer: Lexer<Tok>,
-    cache: Cache,
+    cache: StateCache,
     errors: Vec<Error>,
 }
 
+/// Either a [`Cache`] owned by one parse or a [`SharedCache`] used by parses
+/// on many threads.
+pub(crate) type StateCache = Box<dyn GreenBuilder + Send>;
+
 impl<Tok> State<Tok>
 where
     Tok: TokenKind,
 {
-    fn new(lexer: Lexer<Tok>) -> Self {
+    fn new(lexer: Lexer<Tok>, cache: StateCache) -> Self {
         Self {
             lexer,
-            cache: Default::default(),
+            cache,
             errors: Default::default(),
         }
     }
@@ -25,8 +31,21 @@ where
     }
 
     pub fn parse(lexer: Lexer<Tok>, parser: impl Parser<Tok>) -> ParseResult {
+        Self::parse_with(lexer, parser, Box::new(Cache::default()))
+    }
+
+    /// Parses interning into `cache`, which other threads may use at the same time.
+    pub fn parse_shared(
+        lexer: Lexer<Tok>,
+        parser: impl Parser<Tok>,
+        cache: Arc<SharedCache>,
+    ) -> ParseResult {
+        Self::parse_with(lexer, parser, Box::new(cache))
+    }
+
+    fn parse_with(lexer: Lexer<Tok>, parser: impl Parser<Tok>, cache: StateCache) -> ParseResult {
         let ctx = Context::default();
-        let (root, state) = parser.parse(Self::new(lexer), &ctx);
+        let (root, state) = parser.parse(Self::new(lexer, cache), &ctx);
 
         ParseResult {
             root,
@@ -55,11 +74,89 @@ where
         self.errors.push(err);
     }
 
-    pub(crate) fn cache(&mut self) -> &mut Cache {
-        &mut self.cache
+    pub(crate) fn cache(&mut self) -> &mut dyn GreenBuilder {
+        &mut *self.cache
     }
 
     pub(crate) fn builder<'a>(self, ctx: &'a Context<'a, Tok>) -> crate::Builder<'a, Tok> {
         crate::Builder::new(self, ctx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use microtree::SharedCache;
+
+    use crate::{parsers::any_token, Builder, Lexer, Parser, SmolStr, State, TokenKind};
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    struct Letter;
+
+    impl std::fmt::Display for Letter {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "letter")
+        }
+    }
+
+    impl TokenKind for Letter {
+        type Extra = ();
+
+        fn is_mergeable(self, _other: Self) -> bool {
+            false
+        }
+
+        fn lex(lexer: &mut Lexer<Self>) -> Option<(Self, SmolStr)> {
+            let input = lexer.input_mut();
+            input.as_ref().chars().next()?;
+            Some((Letter, input.chomp(1)))
+        }
+    }
+
+    fn word() -> impl Parser<Letter> {
+        |builder: Builder<Letter>| {
+            let mut builder = builder.node().name("Word");
+            while builder.peek_token().is_some() {
+                builder = builder.parse(any_token());
+            }
+            builder.finish()
+        }
+    }
+
+    #[test]
+    fn parse_shared() {
+        let cache = Arc::new(SharedCache::default());
+        let roots = std::thread::scope(|scope| {
+            let handles = (0..4)
+                .map(|_| {
+                    let cache = cache.clone();
+                    scope.spawn(move || {
+                        State::parse_shared(Lexer::new("abba"), word(), cache)
+                            .root
+                            .unwrap()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let owned = State::parse(Lexer::new("abba"), word()).root.unwrap();
+        assert_eq!(owned, roots[0]);
+        assert!(!owned.ptr_eq(&roots[0]));
+        assert_eq!("abba", roots[0].to_string());
+        for root in &roots {
+            assert!(root.ptr_eq(&roots[0]));
+        }
+        let letters = roots[0].children().collect::<Vec<_>>();
+        assert!(letters[0].ptr_eq(&letters[3]));
+        assert!(letters[1].ptr_eq(&letters[2]));
+
+        let stats = cache.stats();
+        assert_eq!(3, stats.entries);
+        assert_eq!(3, stats.misses);
+    }
+}